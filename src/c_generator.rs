@@ -1,28 +1,258 @@
+use crate::constant::{self, Constant};
+use crate::diagnostic::{self, Diagnostic};
+use crate::module::{Import, Role};
 use crate::parse::Node;
 use crate::parse::NodeKind;
-use crate::token::Type;
-use std::collections::HashMap;
-use std::process::exit;
+use crate::parse::RAW_DATA_TYPE_C;
+use crate::parse::{
+    constructor_params, default_value, field_assignment, insert_field_initializers,
+    CONSTRUCTOR_NAME,
+};
+use crate::parse::{precedence, PRECEDENCE_ATOM, PRECEDENCE_COMPARE, PRECEDENCE_UNARY};
+use crate::resolve::Bindings;
+use crate::token::{Span, Type};
+use crate::typecheck::{Ty, Types};
+use std::collections::{HashMap, HashSet};
 
-const CONST_VARIABLE_RESERV: i32 = 0;
-const CONST_FUNCTION_RESERV: i32 = 1;
+// 文字列の埋め込み式を printf 形式にした結果を返すヘルパー (print 以外で使われたときに出力する)
+// 結果は値ごとに必要な長さだけ確保する。引数として渡して呼び出しの中で使い終わるものは文の後で解放し、
+// 変数や戻り値に入れたものはどこから参照されるか分からないので解放しない
+const FORMAT_HELPER: &str = "static char *tf_format(const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+    int length = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+    char *buffer = malloc(length + 1);
+    if (buffer == NULL) {
+        fprintf(stderr, \"out of memory\\n\");
+        exit(1);
+    }
+    va_start(args, format);
+    vsnprintf(buffer, length + 1, format, args);
+    va_end(args);
+    return buffer;
+}
+
+";
 
 fn str_to_string(s: &str) -> String {
     s.to_string()
 }
 
+// C の文字列リテラルの中身としてエスケープする
+fn escape_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\r' => escaped += "\\r",
+            // 8進数は3桁で書けば後ろの数字とつながらない
+            ch if ch.is_control() && (ch as u32) < 0x100 => {
+                escaped += &format!("\\{:03o}", ch as u32)
+            }
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// `list<int>` の要素の型
+fn list_element(v_type: &str) -> Option<&str> {
+    v_type.strip_prefix("list<")?.strip_suffix('>')
+}
+
+// `map<string, int>` のキーと値の型
+fn map_types(v_type: &str) -> Option<(&str, &str)> {
+    v_type
+        .strip_prefix("map<")?
+        .strip_suffix('>')?
+        .split_once(", ")
+}
+
+// map はキーと値の配列を TF_MAP_CAPACITY 個ずつ確保し、キーを線形探索する
+// (クラスのフィールドでも使うので、ヘッダーに置く)
+const MAP_CAPACITY: usize = 64;
+
+fn map_helper(key_type: &str, suffix: &str) -> String {
+    let equal = if key_type == "const char *" {
+        "strcmp(keys[i], key) == 0"
+    } else {
+        "keys[i] == key"
+    };
+    format!(
+        "static int tf_map_index_{suffix}({key_type} keys[], int len, {key_type} key) {{
+    for (int i = 0; i < len; i++) {{
+        if ({equal}) {{
+            return i;
+        }}
+    }}
+    fprintf(stderr, \"key not found in map\\n\");
+    exit(1);
+}}
+
+static int tf_map_slot_{suffix}({key_type} keys[], int *len, {key_type} key) {{
+    for (int i = 0; i < *len; i++) {{
+        if ({equal}) {{
+            return i;
+        }}
+    }}
+    if (*len == TF_MAP_CAPACITY) {{
+        fprintf(stderr, \"map is full\\n\");
+        exit(1);
+    }}
+    keys[*len] = key;
+    return (*len)++;
+}}
+
+"
+    )
+}
+
+// switch の中からループを抜ける break があるか (C の break は switch だけを抜けてしまう)
+fn switch_breaks(node: &Node, label: Option<&str>, in_switch: bool) -> bool {
+    match &node.kind {
+        Some(NodeKind::Break(target)) => {
+            in_switch && (target.is_none() || target.as_deref() == label)
+        }
+        Some(NodeKind::While { .. } | NodeKind::For { .. }) => false,
+        Some(NodeKind::Match { .. }) => node
+            .children()
+            .into_iter()
+            .any(|child| switch_breaks(child, label, true)),
+        _ => node
+            .children()
+            .into_iter()
+            .any(|child| switch_breaks(child, label, in_switch)),
+    }
+}
+
+// そのまま出力する C のコード
+// 関数の中で、引数 name が呼び出しの後まで残る場所 (変数・フィールド・戻り値・他の関数の引数など) に入るか
+fn escapes(node: &Node, name: &str) -> bool {
+    // 書式に埋め込む値、print の引数、比較の両辺はその場で使い終わる
+    let borrows = match &node.kind {
+        Some(NodeKind::FormatStr(_) | NodeKind::Compare { .. }) => true,
+        Some(NodeKind::Call { function_name, .. }) => function_name == "print",
+        // 直接記入した C のコードで何をしているかは分からない
+        Some(NodeKind::RawLanguage { .. }) => return true,
+        _ => false,
+    };
+    let is_assign = matches!(node.kind, Some(NodeKind::Assign { .. }));
+    node.children()
+        .into_iter()
+        .enumerate()
+        .any(|(i, child)| match &child.kind {
+            // 引数への代入は、渡された値を残さない
+            Some(NodeKind::Str(used)) if used == name => !(borrows || is_assign && i == 0),
+            _ => escapes(child, name),
+        })
+}
+
+// 引数ごとに、呼び出しの中で使い終わる (呼び出しの後で解放してよい) か
+fn borrowed_params(params: &[Node], body: &Node) -> Vec<bool> {
+    params
+        .iter()
+        .map(|param| match &param.kind {
+            Some(NodeKind::Param { name, .. }) => !escapes(body, name),
+            _ => false,
+        })
+        .collect()
+}
+
+fn raw_c(raw_data: String) -> Node {
+    Node {
+        kind: Some(NodeKind::RawLanguage {
+            language_type: RAW_DATA_TYPE_C,
+            raw_data,
+        }),
+        span: Default::default(),
+    }
+}
+
+// 構造体にしたクラス
+#[derive(Default, Clone)]
+struct ClassLayout {
+    base: Option<String>,
+    // フィールドの名前と型 (このクラスで定義されたものだけ)
+    fields: HashMap<String, String>,
+    // 関数ポインタの表に入るメソッドと、それを最初に定義したクラス (基底クラスのものが先)
+    slots: Vec<(String, String)>,
+    // メソッドと、このクラスで呼ばれる実装を定義したクラス
+    implementations: HashMap<String, String>,
+    constructor: Vec<Node>,
+    // 継承に関わるクラスは関数ポインタの表 (`<名前>_methods`) を持つ
+    is_virtual: bool,
+}
+
+#[allow(non_camel_case_types)]
 pub struct C_Generator {
+    pub diagnostics: Vec<Diagnostic>,
+    pub output_files: Vec<String>,
+    pub role: Role,
+    // 型検査で求めた式の型
+    pub types: Types,
+    // 名前を使った場所と宣言の場所 (resolve で結びつけたもの)
+    pub bindings: Bindings,
+    // import されるファイルの名前 (拡張子を .h と .c にして出力する)
+    filename: String,
+    // ヘッダーに出力する宣言
+    header_buf: String,
     tabs_counter: i32,
     source_buf: String,
     op_preset: HashMap<Type, String>,
-    get_variable_or_function: HashMap<String, i32>,
     is_sucsess_type_test: bool,
-    now_identifier: String,
+    // 変数の名前と型。名前で引くのは self や import した定数など、このファイルに宣言がないものだけ
+    variable_types: HashMap<String, String>,
+    // 宣言の場所と、宣言した変数の名前と型
+    declared_types: HashMap<Span, (String, String)>,
+    includes: Vec<String>,
+    helpers: String,
+    loop_labels: Vec<Option<String>>,
+    function_params: HashMap<String, Vec<Node>>,
+    // 関数とメソッド (同じ名前のメソッドはまとめる) の引数ごとの、呼び出しの中で使い終わるか
+    borrowed_params: HashMap<String, Vec<bool>>,
+    borrowed_method_params: HashMap<String, Vec<bool>>,
+    // 生成中の文で引数として作った書式文字列の変数 (文の後で解放する)。None の文では作らない
+    format_temporaries: Option<Vec<String>>,
+    // 生成中の文の前に置く一時変数の宣言 (メソッドを呼ぶ値を入れる)。文の外では None
+    value_temporaries: Option<Vec<String>>,
+    temporary_counter: usize,
+    classes: HashMap<String, ClassLayout>,
+    // 基底クラスとして使われているクラス
+    extended: HashSet<String>,
+    // 生成中のメソッドが属するクラス
+    now_class: Option<String>,
+    // 構造体へのポインタとして受け取った変数 (self とクラス型の引数)
+    pointer_variables: HashSet<String>,
+    enums: HashSet<String>,
+    // いちばん内側のループの中で入れ子になっている switch の数
+    switch_depth: usize,
+    // ラベルのないループに付けたラベルの数
+    loop_counter: usize,
+    constants: HashMap<String, Constant>,
+    // import した .tf ファイルの名前
+    modules: HashSet<String>,
+    // まだ生成していない関数と、生成中の定義の始まり (定義より前の呼び出しを見つける)
+    pending_functions: HashSet<String>,
+    definition_start: usize,
+    // 定義より前で呼ばれた関数と、プロトタイプを置く位置
+    forward_calls: HashMap<String, usize>,
+    prototypes: Vec<(usize, String)>,
 }
 
 impl C_Generator {
     pub fn new() -> Self {
         Self {
+            diagnostics: Vec::new(),
+            output_files: Vec::new(),
+            role: Role::Program,
+            types: HashMap::new(),
+            bindings: HashMap::new(),
+            filename: String::new(),
+            header_buf: String::new(),
             tabs_counter: 0,
             source_buf: str_to_string(""),
             op_preset: [
@@ -32,13 +262,110 @@ impl C_Generator {
                 (Type::Minus, "-"),
                 (Type::Asterisk, "*"),
                 (Type::Slash, "/"),
+                (Type::LessEqual, "<="),
+                (Type::GreaterEqual, ">="),
+                (Type::EqualEqual, "=="),
+                (Type::NotEqual, "!="),
+                (Type::And, "&&"),
+                (Type::Or, "||"),
+                (Type::Not, "!"),
             ]
             .into_iter()
             .map(|(k, v)| (k, v.to_string()))
             .collect(),
-            get_variable_or_function: HashMap::new(),
             is_sucsess_type_test: true,
-            now_identifier: "".to_string(),
+            variable_types: HashMap::new(),
+            declared_types: HashMap::new(),
+            includes: Vec::new(),
+            helpers: String::new(),
+            loop_labels: Vec::new(),
+            function_params: HashMap::new(),
+            borrowed_params: HashMap::new(),
+            borrowed_method_params: HashMap::new(),
+            format_temporaries: None,
+            value_temporaries: None,
+            temporary_counter: 0,
+            classes: HashMap::new(),
+            extended: HashSet::new(),
+            now_class: None,
+            pointer_variables: HashSet::new(),
+            enums: HashSet::new(),
+            switch_depth: 0,
+            loop_counter: 0,
+            constants: HashMap::new(),
+            modules: HashSet::new(),
+            pending_functions: HashSet::new(),
+            definition_start: 0,
+            forward_calls: HashMap::new(),
+            prototypes: Vec::new(),
+        }
+    }
+
+    // import されるファイル用 (宣言をヘッダーに分けて、標準出力ではなくファイルに書き出す)
+    pub fn new_module(filename: String) -> Self {
+        Self {
+            role: Role::Module,
+            filename,
+            ..Self::new()
+        }
+    }
+
+    // 他のファイルで基底クラスとして使われるクラスも、関数ポインタの表を持たせる
+    pub fn extend_classes(&mut self, root: &Node) {
+        if let Some(NodeKind::Root { function_define_s }) = &root.kind {
+            for ast in function_define_s {
+                if let Some(NodeKind::Class {
+                    base: Some((base, _)),
+                    ..
+                }) = &ast.kind
+                {
+                    self.extended.insert(base.clone());
+                }
+            }
+        }
+    }
+
+    // 先に生成したモジュールのヘッダーを読み込み、関数やクラスを使えるようにする
+    pub fn import_module(&mut self, import: &Import, module: &C_Generator) {
+        for (constant, value) in &module.constants {
+            self.constants.insert(constant.clone(), value.clone());
+            if let Some(v_type) = module.variable_types.get(constant) {
+                self.variable_types.insert(constant.clone(), v_type.clone());
+            }
+        }
+        for (class_name, layout) in &module.classes {
+            self.classes.insert(class_name.clone(), layout.clone());
+        }
+        for (function, params) in &module.function_params {
+            self.function_params
+                .insert(function.clone(), params.clone());
+        }
+        self.enums.extend(module.enums.iter().cloned());
+        self.use_include(&format!("#include \"{}.h\"", import.path.replace('.', "/")));
+        self.modules.insert(import.path.clone());
+    }
+
+    // 同じファイルにまとめたモジュールは、定義がすでに前にある
+    pub fn bundle_module(&mut self, path: &str) {
+        self.modules.insert(path.to_string());
+    }
+
+    // mark から後に出力したものを、import されるファイルではヘッダーに移す
+    fn move_to_header(&mut self, mark: usize) {
+        if self.role == Role::Module {
+            let declaration = self.source_buf.split_off(mark);
+            self.header_buf += &declaration;
+        }
+    }
+
+    fn write_output(&mut self, filename: String, content: String) {
+        if let Err(e) = std::fs::write(&filename, content) {
+            self.diagnostics.push(Diagnostic::error(
+                diagnostic::OUTPUT_WRITE_FAILED,
+                vec![filename, e.to_string()],
+            ));
+        } else {
+            self.output_files.push(filename);
         }
     }
 
@@ -46,11 +373,59 @@ impl C_Generator {
         self.source_buf = format!("{}{}", self.source_buf, data);
     }
 
-    pub fn exec_argument(&mut self, params: Vec<Node>) {
-        for p in &params {
-            self.generator(p.clone());
-            self.get_variable_or_function
-                .insert(self.now_identifier.clone(), CONST_VARIABLE_RESERV);
+    // callee は呼ぶ関数 (引数の型を調べる)、borrowed は引数ごとの、呼び出しの中で使い終わるか
+    // (書式文字列を文の後で解放できる)
+    pub fn exec_argument(&mut self, params: Vec<Node>, callee: &str, borrowed: &[bool]) {
+        for (i, p) in params.iter().enumerate() {
+            // クラスの変数は構造体のポインタを渡す
+            if let Some(NodeKind::Str(name)) = &p.kind {
+                if self.class_of(name, p.span).is_some() && !self.pointer_variables.contains(name) {
+                    self.add_source_buf("&".to_string());
+                }
+            }
+            let is_temporary = borrowed.get(i) == Some(&true)
+                && matches!(p.kind, Some(NodeKind::FormatStr(_)))
+                && self.format_temporaries.is_some();
+            if let Some(NodeKind::List(items)) = &p.kind {
+                let element = self
+                    .function_params
+                    .get(callee)
+                    .and_then(|params| params.get(i))
+                    .and_then(|param| match &param.kind {
+                        Some(NodeKind::Param { ty: Some(ty), .. }) => {
+                            list_element(ty).map(str_to_string)
+                        }
+                        _ => None,
+                    })
+                    .or_else(|| match self.types.get(&p.span) {
+                        Some(Ty::List(element)) if **element != Ty::Unknown => {
+                            Some(element.to_string())
+                        }
+                        _ => None,
+                    });
+                self.list_argument(items.clone(), element, p.span);
+            } else if is_temporary {
+                self.temporary_counter += 1;
+                let temporary = format!("tf_text_{}", self.temporary_counter);
+                self.add_source_buf(format!("({} = ", temporary));
+                self.generator(p.clone());
+                self.add_source_buf(")".to_string());
+                if let Some(temporaries) = &mut self.format_temporaries {
+                    temporaries.push(temporary);
+                }
+            } else {
+                self.generator(p.clone());
+            }
+
+            // 配列を渡すときは長さも一緒に渡す
+            if let Some(NodeKind::Str(name)) = &p.kind {
+                if self
+                    .variable_type(name, p.span)
+                    .is_some_and(|v_type| list_element(v_type).is_some())
+                {
+                    self.add_source_buf(format!(", {}_len", name));
+                }
+            }
 
             if Some(p) != params.last() {
                 self.add_source_buf(", ".to_string());
@@ -60,16 +435,496 @@ impl C_Generator {
         }
     }
 
+    // 配列の引数に書いたリストは `(int[]){4, 5}, 2` のように複合リテラルと長さにする
+    fn list_argument(&mut self, items: Vec<Node>, element: Option<String>, span: Span) {
+        let Some(element) = element else {
+            self.unsupported("`[...]`".to_string(), span);
+            return;
+        };
+        let length = items.len();
+        if length == 0 {
+            // C では空の配列は作れない
+            self.use_include("#include <stddef.h>");
+            self.add_source_buf("NULL, 0".to_string());
+            return;
+        }
+        let element = self.c_type(&element);
+        self.add_source_buf(format!("({}[]){{", element));
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                self.add_source_buf(", ".to_string());
+            }
+            self.generator(item);
+        }
+        self.add_source_buf(format!("}}, {}", length));
+    }
+
     pub fn get_identifier(&mut self, type_data: Type) -> String {
         let nothing = String::from("");
 
-        if let Type::Identifier(word) = type_data.clone() {
-            return word;
+        if let Type::Identifier(word) = type_data {
+            word
+        } else {
+            nothing
+        }
+    }
+
+    // 優先順位が min_precedence より低い式は括弧で囲んで出力する
+    fn generate_operand(&mut self, node: Node, min_precedence: u8) {
+        if node.precedence() < min_precedence {
+            self.add_source_buf("(".to_string());
+            self.generator(node);
+            self.add_source_buf(")".to_string());
+        } else {
+            self.generator(node);
+        }
+    }
+
+    fn use_include(&mut self, header: &str) {
+        if !self.includes.iter().any(|include| include == header) {
+            self.includes.push(header.to_string());
+        }
+    }
+
+    fn use_helper(&mut self, helper: String) {
+        if !self.helpers.contains(&helper) {
+            self.helpers += &helper;
+        }
+    }
+
+    // map の探索に使うヘルパーを出力し、その名前に付ける型名を返す
+    fn use_map_helper(&mut self, key_type: &str) -> String {
+        let suffix = match key_type {
+            "string" => "str".to_string(),
+            _ => key_type.to_string(),
+        };
+        let key_type = self.c_type(key_type);
+        self.use_include("#include <stdio.h>");
+        self.use_include("#include <stdlib.h>");
+        if key_type == "const char *" {
+            self.use_include("#include <string.h>");
+        }
+        self.use_include(&format!("#define TF_MAP_CAPACITY {}", MAP_CAPACITY));
+        self.use_helper(map_helper(&key_type, &suffix));
+        suffix
+    }
+
+    // 初期値の要素が TF_MAP_CAPACITY 個に収まらなければ報告する
+    fn check_map_capacity(&mut self, name: &str, length: usize, span: Span) -> bool {
+        if length <= MAP_CAPACITY {
+            return true;
+        }
+        self.diagnostics.push(
+            Diagnostic::error(
+                diagnostic::MAP_CAPACITY_EXCEEDED,
+                vec![
+                    name.to_string(),
+                    length.to_string(),
+                    MAP_CAPACITY.to_string(),
+                ],
+            )
+            .with_label(span, "label.map_capacity", vec![]),
+        );
+        self.is_sucsess_type_test = false;
+        false
+    }
+
+    fn declare_variable(&mut self, name: &str, v_type: &str, span: Span) {
+        self.variable_types
+            .insert(name.to_string(), v_type.to_string());
+        self.declared_types
+            .insert(span, (name.to_string(), v_type.to_string()));
+    }
+
+    // span で使った変数の型。同じ名前の別の変数と取り違えないように、resolve で結びつけた宣言から引く
+    fn variable_type(&self, name: &str, span: Span) -> Option<&String> {
+        let declared = self
+            .bindings
+            .get(&span)
+            .and_then(|declaration| self.declared_types.get(declaration));
+        match declared {
+            Some((declared, v_type)) if declared == name => Some(v_type),
+            _ => self.variable_types.get(name),
+        }
+    }
+
+    // `m[key]` の m が map 型の変数なら、その名前とキーの型
+    fn map_access(&self, target: &Node) -> Option<(String, String)> {
+        match &target.kind {
+            Some(NodeKind::Str(name)) => {
+                let (key_type, _) = map_types(self.variable_type(name, target.span)?)?;
+                Some((name.clone(), key_type.to_string()))
+            }
+            // フィールドの map は `a.items_keys` のように構造体の中の配列を使う
+            Some(NodeKind::Field { target, name }) => {
+                let Some(NodeKind::Str(variable)) = &target.kind else {
+                    return None;
+                };
+                let class_name = self.class_of(variable, target.span)?;
+                let (depth, v_type) = self.find_field(&class_name, name)?;
+                let (key_type, _) = map_types(&v_type)?;
+                let path = format!("{}{}", self.member_path(variable, depth), name);
+                Some((path, key_type.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    // for 文で回す配列と要素の型 (map はキーを回す)
+    fn iteration(&self, name: &str, span: Span) -> Option<(String, String)> {
+        let v_type = self.variable_type(name, span)?;
+        if let Some(element) = list_element(v_type) {
+            Some((name.to_string(), element.to_string()))
+        } else {
+            let (key_type, _) = map_types(v_type)?;
+            Some((format!("{}_keys", name), key_type.to_string()))
+        }
+    }
+
+    // 変数がクラス型なら、そのクラス名
+    fn class_of(&self, name: &str, span: Span) -> Option<String> {
+        let v_type = self.variable_type(name, span)?;
+        self.classes.contains_key(v_type).then(|| v_type.clone())
+    }
+
+    // クラスから基底クラスへたどったクラス名の列 (先頭は class_name)
+    fn ancestors(&self, class_name: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut next = Some(class_name.to_string());
+        while let Some(name) = next {
+            next = self
+                .classes
+                .get(&name)
+                .and_then(|layout| layout.base.clone());
+            chain.push(name);
+        }
+        chain
+    }
+
+    // フィールドが何段上の基底クラスで定義されているかと、その型
+    fn find_field(&self, class_name: &str, name: &str) -> Option<(usize, String)> {
+        self.ancestors(class_name)
+            .iter()
+            .enumerate()
+            .find_map(|(depth, class_name)| {
+                let v_type = self.classes.get(class_name)?.fields.get(name)?;
+                Some((depth, v_type.clone()))
+            })
+    }
+
+    // 基底クラスの部分へ depth 段たどったメンバーの前置き (`a.base.`, `self->base.base.`)
+    fn member_path(&self, variable: &str, depth: usize) -> String {
+        let access = if self.pointer_variables.contains(variable) {
+            "->"
         } else {
-            return nothing;
+            "."
+        };
+        format!("{}{}{}", variable, access, "base.".repeat(depth))
+    }
+
+    // クラスの変数を depth 段上の基底クラスへのポインタにする
+    fn upcast(&self, variable: &str, depth: usize) -> String {
+        match depth {
+            0 if self.pointer_variables.contains(variable) => variable.to_string(),
+            0 => format!("&{}", variable),
+            _ => format!("&{}base", self.member_path(variable, depth - 1)),
         }
     }
 
+    // メソッドを呼ぶ値を文の前で宣言した一時変数に入れる。`(tf_receiver_1 = &a.b, ` まで出力し、
+    // 一時変数の名前を返す (呼び出しの後に `)` が要る)。関数の戻り値は値のまま入れる
+    fn receiver_temporary(&mut self, value: Node) -> Option<String> {
+        let Some(Ty::Class(class_name)) = self.types.get(&value.span).cloned() else {
+            return None;
+        };
+        let is_value = matches!(
+            value.kind,
+            Some(NodeKind::Call { .. } | NodeKind::MethodCall { .. })
+        );
+        self.temporary_counter += 1;
+        let (temporary, declaration) = if is_value {
+            let temporary = format!("tf_value_{}", self.temporary_counter);
+            let declaration = format!("{} {};", class_name, temporary);
+            (temporary, declaration)
+        } else {
+            let temporary = format!("tf_receiver_{}", self.temporary_counter);
+            let declaration = format!("{} *{};", class_name, temporary);
+            (temporary, declaration)
+        };
+        self.value_temporaries.as_mut()?.push(declaration);
+        self.add_source_buf(format!("({} = ", temporary));
+        if is_value {
+            self.generator(value);
+        } else {
+            self.add_source_buf("&".to_string());
+            self.generate_operand(value, PRECEDENCE_UNARY);
+            self.pointer_variables.insert(temporary.clone());
+        }
+        self.add_source_buf(", ".to_string());
+        self.variable_types.insert(temporary.clone(), class_name);
+        Some(temporary)
+    }
+
+    // 値が class_name のクラスか派生クラスなら、何段下の派生クラスか
+    fn derived_depth(&self, class_name: &str, value: &Node) -> Option<usize> {
+        let Some(Ty::Class(found)) = self.types.get(&value.span) else {
+            return None;
+        };
+        self.ancestors(found)
+            .iter()
+            .position(|ancestor| ancestor == class_name)
+    }
+
+    // 引数の型が基底クラスなら、派生クラスの変数はその部分へのポインタにして渡す
+    fn upcast_arguments(&self, function_name: &str, args: Vec<Node>) -> Vec<Node> {
+        let params = self
+            .function_params
+            .get(function_name)
+            .cloned()
+            .unwrap_or_default();
+        args.into_iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                let expected = match params.get(i).and_then(|param| param.kind.as_ref()) {
+                    Some(NodeKind::Param { ty: Some(ty), .. }) => ty,
+                    _ => return arg,
+                };
+                let depth = match &arg.kind {
+                    Some(NodeKind::Str(variable)) => {
+                        self.class_of(variable, arg.span).and_then(|class_name| {
+                            self.ancestors(&class_name)
+                                .iter()
+                                .position(|class_name| class_name == expected)
+                                .filter(|depth| *depth > 0)
+                                .map(|depth| (variable, depth))
+                        })
+                    }
+                    _ => None,
+                };
+                match depth {
+                    Some((variable, depth)) => Node {
+                        kind: Some(NodeKind::Str(self.upcast(variable, depth))),
+                        span: Span::default(),
+                    },
+                    None => arg,
+                }
+            })
+            .collect()
+    }
+
+    // `a.f()` で呼ぶ関数、引数の定義を引く名前、a を渡す式
+    fn method_target(
+        &self,
+        variable: &str,
+        span: Span,
+        name: &str,
+    ) -> Option<(String, String, String)> {
+        if variable == "super" {
+            // super.f() は基底クラスの実装を直接呼ぶ
+            let class_name = self.now_class.as_ref()?;
+            let base = self.classes.get(class_name)?.base.clone()?;
+            if name == CONSTRUCTOR_NAME {
+                let function = format!("{}_init", base);
+                return Some((function.clone(), function, self.upcast("self", 1)));
+            }
+            let layout = self.classes.get(&base)?;
+            let function = format!("{}_{}", layout.implementations.get(name)?, name);
+            let (_, declaring) = layout.slots.iter().find(|(slot, _)| slot == name)?;
+            let depth = self
+                .ancestors(class_name)
+                .iter()
+                .position(|class_name| class_name == declaring)?;
+            return Some((function.clone(), function, self.upcast("self", depth)));
+        }
+
+        let class_name = self.class_of(variable, span)?;
+        let layout = self.classes.get(&class_name)?;
+        if !layout.is_virtual {
+            let function = format!("{}_{}", class_name, name);
+            return Some((function.clone(), function, self.upcast(variable, 0)));
+        }
+        // 継承に関わるクラスは関数ポインタの表から呼ぶ
+        let (_, declaring) = layout.slots.iter().find(|(slot, _)| slot == name)?;
+        let implementation = format!("{}_{}", layout.implementations.get(name)?, name);
+        let chain = self.ancestors(&class_name);
+        let depth = chain
+            .iter()
+            .position(|class_name| class_name == declaring)?;
+        let root = chain.last()?;
+        let mut table = format!("{}methods", self.member_path(variable, chain.len() - 1));
+        if declaring != root {
+            table = format!("((const {}_methods *){})", declaring, table);
+        }
+        Some((
+            format!("{}->{}", table, name),
+            implementation,
+            self.upcast(variable, depth),
+        ))
+    }
+
+    // 関数ポインタの表の初期値。基底クラスの表を入れ子にする
+    fn vtable_initializer(&self, class_name: &str, implementation: &str) -> String {
+        let layout = &self.classes[class_name];
+        let implementations = &self.classes[implementation].implementations;
+        let mut entries = Vec::new();
+        if let Some(base) = &layout.base {
+            entries.push(self.vtable_initializer(base, implementation));
+        }
+        for (method, declaring) in &layout.slots {
+            if declaring == class_name {
+                entries.push(format!("{}_{}", implementations[method], method));
+            }
+        }
+        if entries.is_empty() {
+            entries.push("0".to_string());
+        }
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    // C には引数の初期値もキーワード引数もないので、定義の順に並べ直して、
+    // 省略された引数は初期値で補う
+    fn ordered_arguments(
+        &self,
+        function_name: &str,
+        mut args: Vec<Node>,
+        mut kwargs: Vec<(String, Node)>,
+    ) -> Vec<Node> {
+        let params = self
+            .function_params
+            .get(function_name)
+            .cloned()
+            .unwrap_or_default();
+        for param in params.iter().skip(args.len()) {
+            if let Some(NodeKind::Param { name, default, .. }) = &param.kind {
+                match kwargs.iter().position(|(kw, _)| kw == name) {
+                    Some(i) => args.push(kwargs.remove(i).1),
+                    None => args.extend(default.as_deref().cloned()),
+                }
+            }
+        }
+        args
+    }
+
+    // `///` コメントは `//` コメントにする
+    fn generate_doc_comment(&mut self, doc: Option<String>) {
+        if let Some(doc) = doc {
+            let indent = self.get_indent();
+            for line in doc.lines() {
+                if line.is_empty() {
+                    self.add_source_buf(format!("{}//\n", indent));
+                } else {
+                    self.add_source_buf(format!("{}// {}\n", indent, line));
+                }
+            }
+        }
+    }
+
+    fn unsupported(&mut self, construct: String, span: Span) {
+        self.diagnostics.push(
+            Diagnostic::error(
+                diagnostic::UNSUPPORTED_BY_TARGET,
+                vec![construct, "C".to_string()],
+            )
+            .with_label(span, "label.unsupported", vec![]),
+        );
+        self.is_sucsess_type_test = false;
+    }
+
+    // 言語の型名を C の型名にする
+    fn c_type(&mut self, v_type: &str) -> String {
+        match v_type {
+            "string" => "const char *".to_string(),
+            "bool" => {
+                self.use_include("#include <stdbool.h>");
+                "bool".to_string()
+            }
+            _ => v_type.to_string(),
+        }
+    }
+
+    // 値を printf で出力するときの変換指定子
+    fn format_spec(&mut self, node: &Node) -> &'static str {
+        let ty = match self.types.get(&node.span) {
+            Some(Ty::Unknown) | None => match &node.kind {
+                // 型のない引数は int として宣言している
+                Some(NodeKind::Str(name))
+                    if self
+                        .variable_type(name, node.span)
+                        .is_some_and(|ty| ty == "int") =>
+                {
+                    Ty::Int
+                }
+                _ => Ty::Unknown,
+            },
+            Some(ty) => ty.clone(),
+        };
+        match ty {
+            Ty::Int | Ty::Bool | Ty::Enum(_) => "%d",
+            Ty::Float => "%f",
+            Ty::Str => "%s",
+            _ => {
+                // 型がわからないか、数と文字列以外の値は printf の書式を決められない
+                let construct = match &node.kind {
+                    Some(NodeKind::Str(name)) => format!("`{{{}}}`", name),
+                    _ => "`{...}`".to_string(),
+                };
+                let (label, args) = match ty {
+                    Ty::Unknown => ("label.unknown_type", vec![]),
+                    _ => ("label.found_type", vec![ty.to_string()]),
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        diagnostic::UNSUPPORTED_BY_TARGET,
+                        vec![construct, "C".to_string()],
+                    )
+                    .with_label(node.span, label, args),
+                );
+                self.is_sucsess_type_test = false;
+                "%d"
+            }
+        }
+    }
+
+    // 文字列と埋め込み式を printf 形式の書式と引数に分ける
+    fn collect_format(&mut self, node: Node, format: &mut String, values: &mut Vec<Node>) {
+        match node.kind {
+            Some(NodeKind::StrLiteral(text)) => *format += &text.replace('%', "%%"),
+            Some(NodeKind::FormatStr(parts)) => {
+                for part in parts {
+                    self.collect_format(part, format, values);
+                }
+            }
+            _ => {
+                *format += self.format_spec(&node);
+                values.push(node);
+            }
+        }
+    }
+
+    fn generate_format_call(&mut self, function: &str, format: String, values: Vec<Node>) {
+        self.add_source_buf(format!("{}(\"{}\"", function, escape_string(&format)));
+        for value in values {
+            self.add_source_buf(", ".to_string());
+            self.generator(value);
+        }
+        self.add_source_buf(")".to_string());
+    }
+
+    // print(a, b) は printf("%d %d\n", a, b) にする
+    fn generate_print(&mut self, args: Vec<Node>) {
+        self.use_include("#include <stdio.h>");
+        let mut format = String::new();
+        let mut values = Vec::new();
+        for (i, arg) in args.into_iter().enumerate() {
+            if i > 0 {
+                format.push(' ');
+            }
+            self.collect_format(arg, &mut format, &mut values);
+        }
+        format.push('\n');
+        self.generate_format_call("printf", format, values);
+    }
+
     pub fn get_indent(&mut self) -> String {
         let a_indent = "    ";
         let mut indent = str_to_string("");
@@ -81,53 +936,395 @@ impl C_Generator {
         indent
     }
 
+    // ラベル付きの break / continue は goto にする
+    fn generate_jump(&mut self, keyword: &str, label: Option<String>) {
+        // switch の中の break はループのラベルへ goto する
+        let in_switch = keyword == "break" && self.switch_depth > 0;
+        let label = label.or_else(|| {
+            in_switch
+                .then(|| self.loop_labels.last().cloned().flatten())
+                .flatten()
+        });
+        match label {
+            Some(label) if in_switch || self.loop_labels.last() != Some(&Some(label.clone())) => {
+                self.add_source_buf(format!("goto {}_{}", label, keyword));
+            }
+            _ => self.add_source_buf(keyword.to_string()),
+        }
+    }
+
+    // goto の飛び先は、continue ならループ本体の最後、break ならループの直後
+    fn generate_loop_body(&mut self, body: Node, label: Option<String>) {
+        let breaks_in_switch = switch_breaks(&body, label.as_deref(), false);
+        let label = match label {
+            None if breaks_in_switch => {
+                self.loop_counter += 1;
+                Some(format!("tf_loop{}", self.loop_counter))
+            }
+            label => label,
+        };
+        let (is_break, is_continue) = match &label {
+            Some(label) => body.nested_jumps(label),
+            None => (false, false),
+        };
+        let is_break = is_break || breaks_in_switch;
+        self.loop_labels.push(label.clone());
+        let switch_depth = std::mem::take(&mut self.switch_depth);
+        self.generator(body);
+        self.switch_depth = switch_depth;
+        self.loop_labels.pop();
+
+        let indent = self.get_indent();
+        if let Some(label) = label {
+            if is_continue {
+                let closing = format!("{}}}\n", indent);
+                self.source_buf
+                    .truncate(self.source_buf.len() - closing.len());
+                self.add_source_buf(format!("{}    {}_continue:;\n{}", indent, label, closing));
+            }
+            if is_break {
+                self.add_source_buf(format!("{}{}_break:;\n", indent, label));
+            }
+        }
+    }
+
     pub fn generator(&mut self, node: Node) {
-        match node.kind {
-            Some(node_kind) => match node_kind {
+        if let Some(node_kind) = node.kind {
+            match node_kind {
                 NodeKind::Num(num) => {
                     self.add_source_buf(num.to_string());
                 }
+                NodeKind::Float(num) => {
+                    // `{:?}` なら 2.0 も `2.0` と小数点付きで出力される
+                    self.add_source_buf(format!("{:?}", num));
+                }
+                NodeKind::Bool(value) => {
+                    self.use_include("#include <stdbool.h>");
+                    self.add_source_buf(value.to_string());
+                }
                 NodeKind::Str(word) => {
-                    self.now_identifier = word.clone();
                     self.add_source_buf(word);
                 }
+                NodeKind::StrLiteral(text) => {
+                    self.add_source_buf(format!("\"{}\"", escape_string(&text)));
+                }
+                NodeKind::FormatStr(parts) => {
+                    self.use_include("#include <stdarg.h>");
+                    self.use_include("#include <stdio.h>");
+                    self.use_include("#include <stdlib.h>");
+                    self.use_helper(FORMAT_HELPER.to_string());
+                    let mut format = String::new();
+                    let mut values = Vec::new();
+                    for part in parts {
+                        self.collect_format(part, &mut format, &mut values);
+                    }
+                    self.generate_format_call("tf_format", format, values);
+                }
                 NodeKind::Call {
                     function_name,
                     args,
+                    ..
+                } if function_name == "print" => {
+                    self.generate_print(args);
+                }
+                NodeKind::Call { function_name, .. }
+                    if self.classes.contains_key(&function_name) =>
+                {
+                    // 構造体は `Name: v <- Name(...)` の宣言か `v <- Name(...)` の代入でしか作れない
+                    self.unsupported(format!("`{}(...)`", function_name), node.span);
+                }
+                NodeKind::Call {
+                    function_name,
+                    args,
+                    kwargs,
                 } => {
+                    // 名前が見つかるかどうかは resolve で調べてある
+                    if self.pending_functions.contains(&function_name) {
+                        self.forward_calls
+                            .entry(function_name.clone())
+                            .or_insert(self.definition_start);
+                    }
+                    let args = self.ordered_arguments(&function_name, args, kwargs);
+                    let args = self.upcast_arguments(&function_name, args);
+                    let borrowed = self
+                        .borrowed_params
+                        .get(&function_name)
+                        .cloned()
+                        .unwrap_or_default();
                     self.add_source_buf(function_name.clone());
-                    match self.get_variable_or_function.get(&function_name) {
-                        Some(value) => {
-                            if *value == CONST_FUNCTION_RESERV {
-                                // 関数が使われている
-                                self.add_source_buf("(".to_string());
-                                self.exec_argument(args);
-                                self.add_source_buf(")".to_string());
-                            } else {
-                                // 変数が使われている
-                            }
-                        }
+                    self.add_source_buf("(".to_string());
+                    self.exec_argument(args, &function_name, &borrowed);
+                    self.add_source_buf(")".to_string());
+                }
+                NodeKind::Field { target, name }
+                    if matches!(&target.kind,
+                        Some(NodeKind::Str(enum_name)) if self.enums.contains(enum_name)) =>
+                {
+                    // State.Idle は State_Idle にする
+                    self.generate_operand(*target, PRECEDENCE_ATOM);
+                    self.add_source_buf(format!("_{}", name));
+                }
+                NodeKind::Field { target, name } => {
+                    // 基底クラスのフィールドは `a.base.x` のように埋め込んだ構造体をたどる
+                    let path = match &target.kind {
+                        Some(NodeKind::Str(variable)) => self
+                            .class_of(variable, target.span)
+                            .and_then(|class_name| self.find_field(&class_name, &name))
+                            .map(|(depth, _)| self.member_path(variable, depth)),
+                        _ => None,
+                    };
+                    match path {
+                        Some(path) => self.add_source_buf(format!("{}{}", path, name)),
                         None => {
-                            // 関数か変数かわからないものが使われている
-                            println!("Err: {}が定義されていません", function_name);
-                            self.is_sucsess_type_test = false;
+                            // a.b.x のように変数でない値は、型検査で求めた型から基底クラスをたどる
+                            let depth = match self.types.get(&target.span) {
+                                Some(Ty::Class(class_name)) => self
+                                    .find_field(class_name, &name)
+                                    .map_or(0, |(depth, _)| depth),
+                                _ => 0,
+                            };
+                            self.generate_operand(*target, PRECEDENCE_ATOM);
+                            self.add_source_buf(format!(".{}{}", "base.".repeat(depth), name));
                         }
                     }
                 }
-                NodeKind::Pass(word) => self.add_source_buf("pass".to_string()),
+                NodeKind::MethodCall {
+                    target,
+                    name,
+                    args,
+                    kwargs,
+                } => {
+                    // a.f(x) は Class_f(&a, x) にする
+                    // 変数でない値 (a.b.f() や xs[0].f() など) は一時変数に入れてから呼ぶ
+                    let (variable, span, is_temporary) = match target.kind {
+                        Some(NodeKind::Str(variable)) => (variable, target.span, false),
+                        kind => {
+                            let value = Node {
+                                kind,
+                                span: target.span,
+                            };
+                            match self.receiver_temporary(value) {
+                                Some(temporary) => (temporary, Span::default(), true),
+                                None => {
+                                    self.unsupported(format!("`.{}()`", name), node.span);
+                                    return;
+                                }
+                            }
+                        }
+                    };
+                    let method = self.method_target(&variable, span, &name);
+                    let Some((function, implementation, receiver)) = method else {
+                        self.unsupported(format!("`.{}()`", name), node.span);
+                        return;
+                    };
+                    let args = self.ordered_arguments(&implementation, args, kwargs);
+                    let args = self.upcast_arguments(&implementation, args);
+                    let borrowed = self
+                        .borrowed_method_params
+                        .get(&name)
+                        .cloned()
+                        .unwrap_or_default();
+                    self.add_source_buf(format!("{}({}", function, receiver));
+                    if !args.is_empty() {
+                        self.add_source_buf(", ".to_string());
+                    }
+                    self.exec_argument(args, &implementation, &borrowed);
+                    self.add_source_buf(")".to_string());
+                    if is_temporary {
+                        self.add_source_buf(")".to_string());
+                        self.variable_types.remove(&variable);
+                        self.pointer_variables.remove(&variable);
+                    }
+                }
+                NodeKind::Pass(_word) => self.add_source_buf("pass".to_string()),
                 NodeKind::BinaryOp { op, lhs, rhs } => {
-                    self.generator(*lhs);
+                    let precedence = precedence(&op).unwrap_or(PRECEDENCE_ATOM);
+                    self.generate_operand(*lhs, precedence);
+                    self.add_source_buf(format!(" {} ", self.op_preset[&op]));
+                    self.generate_operand(*rhs, precedence + 1);
+                }
+                NodeKind::Logical { lhs, op, rhs } => {
+                    let precedence = precedence(&op).unwrap_or(PRECEDENCE_ATOM);
+                    self.generate_operand(*lhs, precedence);
+                    self.add_source_buf(format!(" {} ", self.op_preset[&op]));
+                    self.generate_operand(*rhs, precedence + 1);
+                }
+                NodeKind::UnaryOp { op, operand } => {
                     self.add_source_buf(self.op_preset[&op].to_string());
-                    self.generator(*rhs);
+                    // C の単項演算子は二項演算子より強く結びつく。`- -x` が `--x` にならないよう入れ子も括弧で囲む
+                    self.generate_operand(*operand, PRECEDENCE_UNARY + 1);
                 }
                 NodeKind::Return(arg) => {
-                    self.add_source_buf("retrun ".to_string());
+                    self.add_source_buf("return ".to_string());
                     self.generator(*arg);
                 }
                 NodeKind::Compare { lhs, op, rhs } => {
-                    self.generator(*lhs);
-                    self.add_source_buf(self.op_preset[&*op].to_string());
-                    self.generator(*rhs);
+                    // C では `==` が `<` より弱いので、比較の中の比較は必ず括弧で囲む
+                    self.generate_operand(*lhs, PRECEDENCE_COMPARE + 1);
+                    self.add_source_buf(format!(" {} ", self.op_preset[&*op]));
+                    self.generate_operand(*rhs, PRECEDENCE_COMPARE + 1);
+                }
+                NodeKind::Let {
+                    v_name,
+                    v_type,
+                    v_formula,
+                    this_is_define,
+                } if map_types(&v_type).is_some() => {
+                    // キー、値、要素数をそれぞれ `<名前>_keys`, `<名前>_values`, `<名前>_len` にする
+                    let entries = match v_formula.kind {
+                        Some(NodeKind::Map(entries)) if this_is_define => entries,
+                        None if !this_is_define => Vec::new(),
+                        _ => {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::UNSUPPORTED_BY_TARGET,
+                                    vec![format!("`{}: {}`", v_type, v_name), "C".to_string()],
+                                )
+                                .with_label(
+                                    node.span,
+                                    "label.unsupported",
+                                    vec![],
+                                ),
+                            );
+                            self.is_sucsess_type_test = false;
+                            return;
+                        }
+                    };
+                    if !self.check_map_capacity(&v_name, entries.len(), v_formula.span) {
+                        return;
+                    }
+                    self.declare_variable(&v_name, &v_type, node.span);
+                    let (key_type, value_type) = map_types(&v_type).unwrap_or_default();
+                    self.use_map_helper(key_type);
+                    let indent = self.get_indent();
+                    let length = entries.len();
+                    let (keys, values): (Vec<Node>, Vec<Node>) = entries.into_iter().unzip();
+
+                    for (suffix, v_type, items) in
+                        [("keys", key_type, keys), ("values", value_type, values)]
+                    {
+                        let c_type = self.c_type(v_type);
+                        self.add_source_buf(format!(
+                            "{} {}_{}[TF_MAP_CAPACITY]",
+                            c_type, v_name, suffix
+                        ));
+                        if !items.is_empty() {
+                            self.add_source_buf(" = ".to_string());
+                            self.generator(Node {
+                                kind: Some(NodeKind::List(items)),
+                                span: v_formula.span,
+                            });
+                        }
+                        self.add_source_buf(format!(";\n{}", indent));
+                    }
+                    self.add_source_buf(format!("int {}_len = {}", v_name, length));
+                }
+                NodeKind::Let {
+                    v_name,
+                    v_type,
+                    v_formula,
+                    this_is_define,
+                } if list_element(&v_type).is_some() => {
+                    // C の配列は長さを持たないので `<名前>_len` を一緒に宣言する
+                    let items = match v_formula.kind {
+                        Some(NodeKind::List(items)) if this_is_define => items,
+                        _ => {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::UNSUPPORTED_BY_TARGET,
+                                    vec![format!("`{}: {}`", v_type, v_name), "C".to_string()],
+                                )
+                                .with_label(
+                                    node.span,
+                                    "label.unsupported",
+                                    vec![],
+                                ),
+                            );
+                            self.is_sucsess_type_test = false;
+                            return;
+                        }
+                    };
+                    self.declare_variable(&v_name, &v_type, node.span);
+                    let element = list_element(&v_type).unwrap_or("int").to_string();
+                    let element = self.c_type(&element);
+                    let length = items.len();
+                    self.add_source_buf(format!("{} {}[{}] = ", element, v_name, length.max(1)));
+                    if items.is_empty() {
+                        self.add_source_buf("{0}".to_string());
+                    } else {
+                        self.generator(Node {
+                            kind: Some(NodeKind::List(items)),
+                            span: v_formula.span,
+                        });
+                    }
+                    let indent = self.get_indent();
+                    self.add_source_buf(format!(
+                        ";\n{}const int {}_len = {}",
+                        indent, v_name, length
+                    ));
+                }
+                NodeKind::Let {
+                    v_name,
+                    v_type,
+                    v_formula,
+                    this_is_define,
+                } if self.classes.contains_key(&v_type) => {
+                    // 派生クラスの値やポインタで受け取った値は構造体にコピーできないので、
+                    // 基底クラスの部分を指すポインタにする (Python と同じく同じものを指す)
+                    let depth = self
+                        .derived_depth(&v_type, &v_formula)
+                        .filter(|_| this_is_define);
+                    let view = match (&v_formula.kind, depth) {
+                        (Some(NodeKind::Str(variable)), Some(depth))
+                            if depth > 0 || self.pointer_variables.contains(variable) =>
+                        {
+                            Some(self.upcast(variable, depth))
+                        }
+                        (_, Some(depth)) if depth > 0 => {
+                            self.unsupported(format!("`{}: {}`", v_type, v_name), node.span);
+                            return;
+                        }
+                        _ => None,
+                    };
+                    self.declare_variable(&v_name, &v_type, node.span);
+                    self.pointer_variables.remove(&v_name);
+                    if let Some(view) = view {
+                        self.pointer_variables.insert(v_name.clone());
+                        self.add_source_buf(format!("{} *{} = {}", v_type, v_name, view));
+                        return;
+                    }
+                    self.add_source_buf(format!("{} {}", v_type, v_name));
+                    match v_formula.kind {
+                        // `Name: v <- Name(x)` は宣言してから `Name_init(&v, x)` を呼ぶ
+                        Some(NodeKind::Call {
+                            function_name,
+                            args,
+                            kwargs,
+                        }) if function_name == v_type => {
+                            let constructor = format!("{}_init", v_type);
+                            let args = self.ordered_arguments(&constructor, args, kwargs);
+                            let mut args = self.upcast_arguments(&constructor, args);
+                            args.insert(
+                                0,
+                                Node {
+                                    kind: Some(NodeKind::Str(v_name)),
+                                    span: v_formula.span,
+                                },
+                            );
+                            let indent = self.get_indent();
+                            self.add_source_buf(format!(";\n{}{}(", indent, constructor));
+                            self.exec_argument(args, "", &[]);
+                            self.add_source_buf(")".to_string());
+                        }
+                        kind if this_is_define => {
+                            self.add_source_buf(" = ".to_string());
+                            self.generator(Node {
+                                kind,
+                                span: v_formula.span,
+                            });
+                        }
+                        _ => {}
+                    }
                 }
                 NodeKind::Let {
                     v_name,
@@ -135,13 +1332,13 @@ impl C_Generator {
                     v_formula,
                     this_is_define,
                 } => {
-                    self.now_identifier = v_name.clone();
+                    self.pointer_variables.remove(&v_name);
+                    self.declare_variable(&v_name, &v_type, node.span);
+                    let v_type = self.c_type(&v_type);
                     self.add_source_buf(v_type);
                     self.add_source_buf(" ".to_string());
                     self.add_source_buf(v_name);
                     if this_is_define {
-                        self.get_variable_or_function
-                            .insert(self.now_identifier.clone(), CONST_VARIABLE_RESERV);
                         self.add_source_buf(" = ".to_string());
                     }
                     self.generator(*v_formula);
@@ -156,12 +1353,348 @@ impl C_Generator {
                     self.generator(*cond);
                     self.add_source_buf(") ".to_string());
                     self.generator(*then);
+                    for elif in elif_then.unwrap_or_default() {
+                        self.generator(elif);
+                    }
+                    if let Some(else_then) = else_then {
+                        // `}` の後ろに続けて `} else {` にする
+                        self.source_buf.pop();
+                        self.add_source_buf(" else ".to_string());
+                        self.generator(*else_then);
+                    }
+                }
+                NodeKind::Elif { cond, then } => {
+                    self.source_buf.pop();
+                    self.add_source_buf(" else if (".to_string());
+                    self.generator(*cond);
+                    self.add_source_buf(") ".to_string());
+                    self.generator(*then);
                 }
+                NodeKind::Match {
+                    value,
+                    arms,
+                    default,
+                } => {
+                    // match は switch にする。case の値は列挙型の値か整数だけ
+                    let indent = self.get_indent();
+                    self.add_source_buf("switch (".to_string());
+                    self.generator(*value);
+                    self.add_source_buf(") {\n".to_string());
+                    self.switch_depth += 1;
+                    let arms = arms.into_iter().filter_map(|arm| match arm.kind {
+                        Some(NodeKind::MatchArm { patterns, body }) => Some((patterns, *body)),
+                        _ => None,
+                    });
+                    for (patterns, mut body) in arms {
+                        let count = patterns.len();
+                        for (i, pattern) in patterns.into_iter().enumerate() {
+                            let is_constant = match &pattern.kind {
+                                Some(NodeKind::Num(_)) => true,
+                                Some(NodeKind::Field { target, .. }) => matches!(&target.kind,
+                                    Some(NodeKind::Str(enum_name)) if self.enums.contains(enum_name)),
+                                _ => false,
+                            };
+                            if !is_constant {
+                                self.unsupported("`match`".to_string(), pattern.span);
+                            }
+                            self.add_source_buf(format!("{}case ", indent));
+                            self.generator(pattern);
+                            if i + 1 < count {
+                                self.add_source_buf(":\n".to_string());
+                            } else {
+                                self.add_source_buf(": ".to_string());
+                            }
+                        }
+                        if let Some(NodeKind::Block(statements)) = &mut body.kind {
+                            let is_jump = matches!(statements.last().and_then(|last| last.kind.as_ref()),
+                                Some(NodeKind::Expr { reserv }) if matches!(reserv.kind,
+                                    Some(NodeKind::Return(_) | NodeKind::Break(_) | NodeKind::Continue(_))));
+                            if !is_jump {
+                                statements.push(raw_c(format!("{}    break;\n", indent)));
+                            }
+                        }
+                        self.generator(body);
+                    }
+                    if let Some(default) = default {
+                        self.add_source_buf(format!("{}default: ", indent));
+                        self.generator(*default);
+                    }
+                    self.switch_depth -= 1;
+                    self.add_source_buf(format!("{}}}\n", indent));
+                }
+                NodeKind::While { cond, body, label } => {
+                    self.add_source_buf("while (".to_string());
+                    self.generator(*cond);
+                    self.add_source_buf(") ".to_string());
+                    self.generate_loop_body(*body, label);
+                }
+                NodeKind::For {
+                    var,
+                    iter,
+                    body,
+                    label,
+                } => match iter.kind {
+                    Some(NodeKind::Range { start, end }) => {
+                        self.declare_variable(&var, "int", node.span);
+                        self.add_source_buf(format!("for (int {} = ", var));
+                        self.generator(*start);
+                        self.add_source_buf(format!("; {} < ", var));
+                        self.generator(*end);
+                        self.add_source_buf(format!("; {}++) ", var));
+                        self.generate_loop_body(*body, label);
+                    }
+                    Some(NodeKind::Str(list)) if self.iteration(&list, iter.span).is_some() => {
+                        // for x in xs は添字のループにして、本体の先頭で x を宣言する
+                        let (array, v_type) = self.iteration(&list, iter.span).unwrap_or_default();
+                        let counter = format!("{}_index", var);
+                        let element = Node {
+                            kind: Some(NodeKind::Let {
+                                v_name: var,
+                                v_type,
+                                v_formula: Box::new(Node {
+                                    kind: Some(NodeKind::Index {
+                                        target: Box::new(Node {
+                                            kind: Some(NodeKind::Str(array)),
+                                            span: iter.span,
+                                        }),
+                                        index: Box::new(Node {
+                                            kind: Some(NodeKind::Str(counter.clone())),
+                                            span: iter.span,
+                                        }),
+                                    }),
+                                    span: iter.span,
+                                }),
+                                this_is_define: true,
+                            }),
+                            // resolve は x を for 文の場所で宣言している
+                            span: node.span,
+                        };
+                        let mut body = *body;
+                        if let Some(NodeKind::Block(statements)) = &mut body.kind {
+                            statements.insert(
+                                0,
+                                Node {
+                                    kind: Some(NodeKind::Expr {
+                                        reserv: Box::new(element),
+                                    }),
+                                    span: iter.span,
+                                },
+                            );
+                        }
+                        self.add_source_buf(format!(
+                            "for (int {0} = 0; {0} < {1}_len; {0}++) ",
+                            counter, list
+                        ));
+                        self.generate_loop_body(body, label);
+                    }
+                    _ => {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::UNSUPPORTED_BY_TARGET,
+                                vec!["`for ... in`".to_string(), "C".to_string()],
+                            )
+                            .with_label(
+                                iter.span,
+                                "label.unsupported",
+                                vec![],
+                            ),
+                        );
+                        self.is_sucsess_type_test = false;
+                    }
+                },
+                NodeKind::Param { name, ty, .. } => {
+                    // 型のない引数は int として扱う
+                    let ty = ty.unwrap_or_else(|| "int".to_string());
+                    self.declare_variable(&name, &ty, node.span);
+                    self.pointer_variables.remove(&name);
+                    if self.classes.contains_key(&ty) {
+                        // 呼び出し側の変数を書き換えられるようにポインタで受け取る
+                        self.pointer_variables.insert(name.clone());
+                        self.add_source_buf(format!("{} *{}", ty, name));
+                    } else if let Some(element) = list_element(&ty) {
+                        let element = self.c_type(element);
+                        self.add_source_buf(format!("{} {}[], int {}_len", element, name, name));
+                    } else if map_types(&ty).is_some() {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::UNSUPPORTED_BY_TARGET,
+                                vec![format!("`{}: {}`", ty, name), "C".to_string()],
+                            )
+                            .with_label(
+                                node.span,
+                                "label.unsupported",
+                                vec![],
+                            ),
+                        );
+                        self.is_sucsess_type_test = false;
+                    } else {
+                        let ty = self.c_type(&ty);
+                        self.add_source_buf(format!("{} {}", ty, name));
+                    }
+                }
+                NodeKind::Assign { lhs, rhs } => {
+                    // m <- {k: v} は要素数を 0 に戻してから 1 つずつ入れる
+                    if let Some((path, key_type)) = self.map_access(&lhs) {
+                        let Some(NodeKind::Map(entries)) = rhs.kind else {
+                            self.unsupported(format!("`{} <- ...`", path), node.span);
+                            return;
+                        };
+                        if !self.check_map_capacity(&path, entries.len(), rhs.span) {
+                            return;
+                        }
+                        let suffix = self.use_map_helper(&key_type);
+                        self.add_source_buf(format!("({}_len = 0", path));
+                        for (key, value) in entries {
+                            self.add_source_buf(format!(
+                                ", {0}_values[tf_map_slot_{1}({0}_keys, &{0}_len, ",
+                                path, suffix
+                            ));
+                            self.generator(key);
+                            self.add_source_buf(")] = ".to_string());
+                            self.generator(value);
+                        }
+                        self.add_source_buf(")".to_string());
+                        return;
+                    }
+                    // ポインタの変数は指す先を替える。派生クラスの値は構造体にコピーできない
+                    if let Some(Ty::Class(class_name)) = self.types.get(&lhs.span).cloned() {
+                        let is_pointer = matches!(&lhs.kind,
+                            Some(NodeKind::Str(name)) if self.pointer_variables.contains(name));
+                        match (&rhs.kind, self.derived_depth(&class_name, &rhs)) {
+                            (Some(NodeKind::Str(variable)), Some(depth)) if is_pointer => {
+                                let view = self.upcast(variable, depth);
+                                self.generator(*lhs);
+                                self.add_source_buf(format!(" = {}", view));
+                                return;
+                            }
+                            (_, Some(depth)) if is_pointer || depth > 0 => {
+                                let found =
+                                    self.types.get(&rhs.span).cloned().unwrap_or(Ty::Unknown);
+                                self.unsupported(
+                                    format!("`{} <- {}`", class_name, found),
+                                    node.span,
+                                );
+                                return;
+                            }
+                            _ => {}
+                        }
+                        // `x <- Name(...)` は `Name_init(&x, ...)` で作り直す
+                        if let Some(NodeKind::Call {
+                            function_name,
+                            args,
+                            kwargs,
+                        }) = &rhs.kind
+                        {
+                            if *function_name == class_name {
+                                let constructor = format!("{}_init", class_name);
+                                let args = self.ordered_arguments(
+                                    &constructor,
+                                    args.clone(),
+                                    kwargs.clone(),
+                                );
+                                let args = self.upcast_arguments(&constructor, args);
+                                self.add_source_buf(format!("{}(&", constructor));
+                                self.generate_operand(*lhs, PRECEDENCE_UNARY);
+                                if !args.is_empty() {
+                                    self.add_source_buf(", ".to_string());
+                                }
+                                self.exec_argument(args, &constructor, &[]);
+                                self.add_source_buf(")".to_string());
+                                return;
+                            }
+                        }
+                    }
+                    let map_slot = match &lhs.kind {
+                        Some(NodeKind::Index { target, index }) => self
+                            .map_access(target)
+                            .map(|access| (access, (**index).clone())),
+                        _ => None,
+                    };
+                    match map_slot {
+                        // m[key] <- value はキーがなければ追加する
+                        Some(((name, key_type), index)) => {
+                            let suffix = self.use_map_helper(&key_type);
+                            self.add_source_buf(format!(
+                                "{0}_values[tf_map_slot_{1}({0}_keys, &{0}_len, ",
+                                name, suffix
+                            ));
+                            self.generator(index);
+                            self.add_source_buf(")]".to_string());
+                        }
+                        None => self.generator(*lhs),
+                    }
+                    self.add_source_buf(" = ".to_string());
+                    self.generator(*rhs);
+                }
+                NodeKind::List(items) => {
+                    self.add_source_buf("{".to_string());
+                    for (i, item) in items.into_iter().enumerate() {
+                        if i > 0 {
+                            self.add_source_buf(", ".to_string());
+                        }
+                        self.generator(item);
+                    }
+                    self.add_source_buf("}".to_string());
+                }
+                NodeKind::Index { target, index } if self.map_access(&target).is_some() => {
+                    let (name, key_type) = self.map_access(&target).unwrap_or_default();
+                    let suffix = self.use_map_helper(&key_type);
+                    self.add_source_buf(format!(
+                        "{0}_values[tf_map_index_{1}({0}_keys, {0}_len, ",
+                        name, suffix
+                    ));
+                    self.generator(*index);
+                    self.add_source_buf(")]".to_string());
+                }
+                NodeKind::Index { target, index } => {
+                    self.generate_operand(*target, PRECEDENCE_ATOM);
+                    self.add_source_buf("[".to_string());
+                    self.generator(*index);
+                    self.add_source_buf("]".to_string());
+                }
+                NodeKind::Break(label) => self.generate_jump("break", label),
+                NodeKind::Continue(label) => self.generate_jump("continue", label),
                 NodeKind::Expr { reserv } => {
                     let indent = self.get_indent().clone();
+                    let statement_start = self.source_buf.len();
                     self.add_source_buf(indent.to_string());
+                    // ブロックで終わる文には `;` を付けない
+                    let is_block_statement = matches!(
+                        reserv.kind,
+                        Some(
+                            NodeKind::If { .. }
+                                | NodeKind::While { .. }
+                                | NodeKind::For { .. }
+                                | NodeKind::Match { .. }
+                        )
+                    );
+                    // 一時変数は文の前で宣言する。引数に渡す書式文字列は文の後で解放する
+                    // (ブロックを持つ文や return の後には解放を置けない)
+                    let can_release =
+                        !is_block_statement && !matches!(reserv.kind, Some(NodeKind::Return(_)));
+                    let outer =
+                        std::mem::replace(&mut self.format_temporaries, can_release.then(Vec::new));
+                    let outer_values = self.value_temporaries.replace(Vec::new());
                     self.generator(*reserv);
-                    self.add_source_buf(";\n".to_string());
+                    if !is_block_statement {
+                        self.add_source_buf(";\n".to_string());
+                    }
+                    let temporaries =
+                        std::mem::replace(&mut self.format_temporaries, outer).unwrap_or_default();
+                    let values = std::mem::replace(&mut self.value_temporaries, outer_values)
+                        .unwrap_or_default();
+                    let declarations: String =
+                        values
+                            .iter()
+                            .map(|declaration| format!("{}{}\n", indent, declaration))
+                            .chain(temporaries.iter().map(|temporary| {
+                                format!("{}char *{} = NULL;\n", indent, temporary)
+                            }))
+                            .collect();
+                    self.source_buf.insert_str(statement_start, &declarations);
+                    for temporary in temporaries {
+                        self.add_source_buf(format!("{}free({});\n", indent, temporary));
+                    }
                 }
                 NodeKind::Block(block) => {
                     self.tabs_counter += 1;
@@ -180,32 +1713,539 @@ impl C_Generator {
                     function_type,
                     function_name,
                     is_menber,
+                    doc,
                 } => {
-                    let identifier = self.get_identifier(function_name);
-                    self.get_variable_or_function
-                        .insert(identifier.clone().to_string(), CONST_FUNCTION_RESERV);
+                    self.generate_doc_comment(doc);
+                    let signature_start = self.source_buf.len();
+                    let mut identifier = self.get_identifier(function_name);
+                    // メソッドは `Class_method(Class *self, ...)` という関数にする
+                    // 基底クラスのメソッドを上書きするときは、表の型に合わせて基底クラスのポインタで受け取る
+                    let receiver = match &self.now_class {
+                        Some(class_name) if is_menber => {
+                            let declaring = self
+                                .classes
+                                .get(class_name)
+                                .and_then(|layout| {
+                                    layout.slots.iter().find(|(slot, _)| *slot == identifier)
+                                })
+                                .map_or(class_name.clone(), |(_, declaring)| declaring.clone());
+                            identifier = if identifier == CONSTRUCTOR_NAME {
+                                format!("{}_init", class_name)
+                            } else {
+                                format!("{}_{}", class_name, identifier)
+                            };
+                            Some((class_name.clone(), declaring))
+                        }
+                        _ => None,
+                    };
+                    self.function_params
+                        .insert(identifier.clone(), params.clone());
 
                     let t = self.get_identifier(function_type);
+                    if list_element(&t).is_some() || map_types(&t).is_some() {
+                        // C の関数は配列を返せない
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::UNSUPPORTED_BY_TARGET,
+                                vec![format!("`{}: {}`", t, identifier), "C".to_string()],
+                            )
+                            .with_label(
+                                node.span,
+                                "label.unsupported",
+                                vec![],
+                            ),
+                        );
+                        self.is_sucsess_type_test = false;
+                    }
+                    let t = self.c_type(&t);
                     self.add_source_buf(t);
                     self.add_source_buf(" ".to_string());
                     self.add_source_buf(identifier.to_string());
                     self.add_source_buf("(".to_string());
-                    self.exec_argument(params);
+                    let mut body = *body;
+                    if let Some((class_name, declaring)) = receiver {
+                        if declaring == class_name {
+                            self.add_source_buf(format!("{} *self", class_name));
+                        } else {
+                            self.add_source_buf(format!("{} *tf_self", declaring));
+                            let cast = format!(
+                                "{}    {1} *self = ({1} *)tf_self;\n",
+                                self.get_indent(),
+                                class_name
+                            );
+                            if let Some(NodeKind::Block(statements)) = &mut body.kind {
+                                statements.insert(0, raw_c(cast));
+                            }
+                        }
+                        if !params.is_empty() {
+                            self.add_source_buf(", ".to_string());
+                        }
+                        self.variable_types.insert("self".to_string(), class_name);
+                        self.pointer_variables.insert("self".to_string());
+                    }
+                    self.exec_argument(params, "", &[]);
                     self.add_source_buf(") ".to_string());
-                    self.generator(*body);
+                    self.pending_functions.remove(&identifier);
+                    let prototype = self.source_buf[signature_start..].trim_end().to_string();
+                    if self.role == Role::Module {
+                        self.header_buf += &format!("{};\n", prototype);
+                    } else if let Some(offset) = self.forward_calls.remove(&identifier) {
+                        self.prototypes.push((offset, format!("{};\n", prototype)));
+                    }
+                    self.generator(body);
+                }
+                NodeKind::Class {
+                    class_name,
+                    base,
+                    fields,
+                    menbers,
+                    doc,
+                } => {
+                    // クラスは構造体とメソッドの関数にする。基底クラスは先頭に `base` として埋め込む
+                    let declaration_start = self.source_buf.len();
+                    self.generate_doc_comment(doc);
+                    let base = base
+                        .map(|(base, _)| base)
+                        .filter(|base| self.classes.contains_key(base));
+                    let mut layout = ClassLayout {
+                        base: base.clone(),
+                        is_virtual: base.is_some() || self.extended.contains(&class_name),
+                        ..Default::default()
+                    };
+                    if let Some(base) = &base {
+                        layout.slots = self.classes[base].slots.clone();
+                        layout.implementations = self.classes[base].implementations.clone();
+                    }
+                    let mut new_slots = Vec::new();
+                    for menber in &menbers {
+                        if let Some(NodeKind::Function {
+                            function_name: Type::Identifier(name),
+                            ..
+                        }) = &menber.kind
+                        {
+                            if name == CONSTRUCTOR_NAME {
+                                continue;
+                            }
+                            if !layout.slots.iter().any(|(slot, _)| slot == name) {
+                                layout.slots.push((name.clone(), class_name.clone()));
+                                new_slots.push(menber.clone());
+                            }
+                            layout
+                                .implementations
+                                .insert(name.clone(), class_name.clone());
+                        }
+                    }
+
+                    let mut members = match &base {
+                        Some(base) => format!("    {} base;\n", base),
+                        None if layout.is_virtual => {
+                            format!("    const {}_methods *methods;\n", class_name)
+                        }
+                        None => String::new(),
+                    };
+                    let mut initializers = Vec::new();
+                    for field in fields {
+                        if let Some(NodeKind::Let {
+                            v_name,
+                            v_type,
+                            v_formula,
+                            this_is_define,
+                        }) = field.kind
+                        {
+                            // map のフィールドはキーと値の配列と要素数にして、コンストラクタで入れる
+                            if let Some((key_type, value_type)) = map_types(&v_type) {
+                                self.use_map_helper(key_type);
+                                for (suffix, v_type) in [("keys", key_type), ("values", value_type)]
+                                {
+                                    let c_type = self.c_type(v_type);
+                                    members += &format!(
+                                        "    {} {}_{}[TF_MAP_CAPACITY];\n",
+                                        c_type, v_name, suffix
+                                    );
+                                }
+                                members += &format!("    int {}_len;\n", v_name);
+                                let value = if this_is_define {
+                                    *v_formula
+                                } else {
+                                    Node {
+                                        kind: Some(NodeKind::Map(Vec::new())),
+                                        span: field.span,
+                                    }
+                                };
+                                initializers.push(field_assignment(&v_name, value));
+                                layout.fields.insert(v_name, v_type);
+                                continue;
+                            }
+                            if list_element(&v_type).is_some() {
+                                self.unsupported(format!("`{}: {}`", v_type, v_name), field.span);
+                                continue;
+                            }
+                            let c_type = self.c_type(&v_type);
+                            members += &format!("    {} {};\n", c_type, v_name);
+                            // 初期値のないフィールドも Python と同じ値から始める
+                            if this_is_define {
+                                initializers.push(field_assignment(&v_name, *v_formula));
+                            } else if let Some(value) = default_value(&v_type, field.span) {
+                                initializers.push(field_assignment(&v_name, value));
+                            }
+                            layout.fields.insert(v_name, v_type);
+                        }
+                    }
+                    if members.is_empty() {
+                        // 空の構造体は C では書けない
+                        members += "    char tf_empty;\n";
+                    }
+
+                    if layout.is_virtual {
+                        self.add_source_buf(format!("typedef struct {0} {0};\n", class_name));
+                        self.add_source_buf(format!("typedef struct {}_methods {{\n", class_name));
+                        if let Some(base) = &base {
+                            self.add_source_buf(format!("    {}_methods base;\n", base));
+                        } else if new_slots.is_empty() {
+                            self.add_source_buf("    char tf_empty;\n".to_string());
+                        }
+                        for method in new_slots {
+                            if let Some(NodeKind::Function {
+                                params,
+                                function_type,
+                                function_name,
+                                ..
+                            }) = method.kind
+                            {
+                                let t = self.get_identifier(function_type);
+                                let t = self.c_type(&t);
+                                let name = self.get_identifier(function_name);
+                                self.add_source_buf(format!(
+                                    "    {} (*{})({} *self",
+                                    t, name, class_name
+                                ));
+                                if !params.is_empty() {
+                                    self.add_source_buf(", ".to_string());
+                                }
+                                self.exec_argument(params, "", &[]);
+                                self.add_source_buf(");\n".to_string());
+                            }
+                        }
+                        self.add_source_buf(format!("}} {}_methods;\n", class_name));
+                        self.add_source_buf(format!("struct {} {{\n{}}};\n", class_name, members));
+                        self.move_to_header(declaration_start);
+                        self.add_source_buf(format!(
+                            "static const {0}_methods {0}_vtable;\n",
+                            class_name
+                        ));
+                    } else {
+                        self.add_source_buf(format!(
+                            "typedef struct {0} {{\n{1}}} {0};\n",
+                            class_name, members
+                        ));
+                        self.move_to_header(declaration_start);
+                    }
+
+                    // 宣言で必ず `Name_init` を呼ぶので、コンストラクタがなければ作る
+                    let inherited = base
+                        .as_ref()
+                        .map(|base| self.classes[base].constructor.clone());
+                    let mut menbers = insert_field_initializers(menbers, initializers, inherited);
+                    layout.constructor = constructor_params(&menbers).unwrap_or_default();
+                    let is_virtual = layout.is_virtual;
+                    self.classes.insert(class_name.clone(), layout);
+
+                    if is_virtual {
+                        // 表はコンストラクタの最後で設定する (基底クラスのコンストラクタに上書きされないように)
+                        let chain = self.ancestors(&class_name);
+                        let root = chain.last().cloned().unwrap_or_default();
+                        let table = if chain.len() == 1 {
+                            format!("&{}_vtable", class_name)
+                        } else {
+                            format!("(const {}_methods *)&{}_vtable", root, class_name)
+                        };
+                        let line = format!(
+                            "{}    self->{}methods = {};\n",
+                            self.get_indent(),
+                            "base.".repeat(chain.len() - 1),
+                            table
+                        );
+                        for menber in &mut menbers {
+                            if let Some(NodeKind::Function {
+                                function_name: Type::Identifier(name),
+                                body,
+                                ..
+                            }) = &mut menber.kind
+                            {
+                                if name == CONSTRUCTOR_NAME {
+                                    if let Some(NodeKind::Block(statements)) = &mut body.kind {
+                                        statements.push(raw_c(line.clone()));
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    self.now_class = Some(class_name.clone());
+                    for menber in menbers {
+                        self.generator(menber);
+                    }
+                    self.now_class = None;
+                    if is_virtual {
+                        let initializer = self.vtable_initializer(&class_name, &class_name);
+                        self.add_source_buf(format!(
+                            "static const {0}_methods {0}_vtable = {1};\n",
+                            class_name, initializer
+                        ));
+                    }
+                }
+                NodeKind::Global {
+                    declaration,
+                    is_const,
+                } => {
+                    let Some(NodeKind::Let {
+                        v_name,
+                        v_type,
+                        v_formula,
+                        this_is_define,
+                    }) = declaration.kind
+                    else {
+                        return;
+                    };
+                    // ファイルの先頭で初期化する値は、コンパイル時に計算できなければならない
+                    if let Some((key_type, value_type)) = map_types(&v_type) {
+                        let entries = match v_formula.kind {
+                            Some(NodeKind::Map(entries)) if this_is_define => entries,
+                            _ if !this_is_define => Vec::new(),
+                            _ => {
+                                self.unsupported(format!("`{}: {}`", v_type, v_name), node.span);
+                                return;
+                            }
+                        };
+                        let mut keys = Vec::new();
+                        let mut values = Vec::new();
+                        for (key, value) in &entries {
+                            let key = constant::evaluate(key, &self.constants)
+                                .map(|c| c.to_node(key.span));
+                            let value = constant::evaluate(value, &self.constants)
+                                .map(|c| c.to_node(value.span));
+                            match (key, value) {
+                                (Ok(key), Ok(value)) => {
+                                    keys.push(key);
+                                    values.push(value);
+                                }
+                                _ => {
+                                    self.unsupported(
+                                        format!("`{}: {}`", v_type, v_name),
+                                        node.span,
+                                    );
+                                    return;
+                                }
+                            }
+                        }
+                        if !self.check_map_capacity(&v_name, entries.len(), v_formula.span) {
+                            return;
+                        }
+                        self.declare_variable(&v_name, &v_type, declaration.span);
+                        self.use_map_helper(key_type);
+                        for (suffix, v_type, items) in
+                            [("keys", key_type, keys), ("values", value_type, values)]
+                        {
+                            let c_type = self.c_type(v_type);
+                            self.add_source_buf(format!(
+                                "static {} {}_{}[TF_MAP_CAPACITY]",
+                                c_type, v_name, suffix
+                            ));
+                            if !items.is_empty() {
+                                self.add_source_buf(" = ".to_string());
+                                self.generator(Node {
+                                    kind: Some(NodeKind::List(items)),
+                                    span: v_formula.span,
+                                });
+                            }
+                            self.add_source_buf(";\n".to_string());
+                        }
+                        self.add_source_buf(format!(
+                            "static int {}_len = {};\n",
+                            v_name,
+                            entries.len()
+                        ));
+                        return;
+                    }
+                    // 初期値のない文字列は NULL ではなく空文字列にする (Python と同じ)
+                    let value = if this_is_define {
+                        constant::evaluate(&v_formula, &self.constants).ok()
+                    } else if v_type == "string" {
+                        Some(Constant::Str(String::new()))
+                    } else {
+                        None
+                    };
+                    if list_element(&v_type).is_some() || (this_is_define && value.is_none()) {
+                        self.unsupported(format!("`{}: {}`", v_type, v_name), node.span);
+                        return;
+                    }
+                    if let (true, Some(value)) = (is_const, &value) {
+                        self.constants.insert(v_name.clone(), value.clone());
+                    }
+                    self.declare_variable(&v_name, &v_type, declaration.span);
+                    let c_type = self.c_type(&v_type);
+                    let declaration_start = self.source_buf.len();
+                    let declaration = match (is_const, v_type.as_str()) {
+                        (true, "string") => "static const char *const".to_string(),
+                        (true, _) => format!("static const {}", c_type),
+                        (false, _) => format!("static {}", c_type),
+                    };
+                    self.add_source_buf(format!("{} {}", declaration, v_name));
+                    if let Some(value) = value {
+                        self.add_source_buf(" = ".to_string());
+                        self.generator(value.to_node(v_formula.span));
+                    }
+                    self.add_source_buf(";\n".to_string());
+                    // 定数はヘッダーに置いて import したファイルからも使えるようにする
+                    if is_const {
+                        self.move_to_header(declaration_start);
+                    }
+                }
+                NodeKind::Enum {
+                    name,
+                    variants,
+                    doc,
+                } => {
+                    // 値の名前は `<型名>_<値>` にする
+                    self.enums.insert(name.clone());
+                    let declaration_start = self.source_buf.len();
+                    self.generate_doc_comment(doc);
+                    let variants: Vec<String> = variants
+                        .into_iter()
+                        .map(|(variant, _)| format!("{}_{}", name, variant))
+                        .collect();
+                    self.add_source_buf(format!(
+                        "typedef enum {0} {{ {1} }} {0};\n",
+                        name,
+                        variants.join(", ")
+                    ));
+                    self.move_to_header(declaration_start);
+                }
+                NodeKind::Import { path, names, alias } => {
+                    // C では名前を付け替えられない。.tf 以外のモジュールは使えないので何も出力しない
+                    let path = path.join(".");
+                    if !self.modules.contains(&path) {
+                        return;
+                    }
+                    let renamed = match alias {
+                        Some(alias) => Some(format!("`import {} as {}`", path, alias)),
+                        None => names.into_iter().flatten().find_map(|(name, local, _)| {
+                            local.map(|local| format!("`{} as {}`", name, local))
+                        }),
+                    };
+                    if let Some(renamed) = renamed {
+                        self.unsupported(renamed, node.span);
+                    }
+                }
+                NodeKind::RawLanguage {
+                    language_type,
+                    raw_data,
+                } if language_type == RAW_DATA_TYPE_C => {
+                    self.add_source_buf(raw_data);
                 }
                 NodeKind::Root { function_define_s } => {
+                    for ast in &function_define_s {
+                        if let Some(NodeKind::Class {
+                            base: Some((base, _)),
+                            ..
+                        }) = &ast.kind
+                        {
+                            self.extended.insert(base.clone());
+                        }
+                    }
+                    // 使う場所より後で定義されている関数も、引数の順番や既定値がわかるようにする
+                    for ast in &function_define_s {
+                        match &ast.kind {
+                            Some(NodeKind::Function {
+                                function_name: Type::Identifier(name),
+                                params,
+                                body,
+                                ..
+                            }) => {
+                                self.function_params.insert(name.clone(), params.clone());
+                                self.borrowed_params
+                                    .insert(name.clone(), borrowed_params(params, body));
+                                self.pending_functions.insert(name.clone());
+                            }
+                            // どのクラスの実装が呼ばれるか分からないので、同じ名前のメソッドのどれもが使い終わる引数だけ
+                            Some(NodeKind::Class { menbers, .. }) => {
+                                for menber in menbers {
+                                    if let Some(NodeKind::Function {
+                                        function_name: Type::Identifier(name),
+                                        params,
+                                        body,
+                                        ..
+                                    }) = &menber.kind
+                                    {
+                                        let borrowed = borrowed_params(params, body);
+                                        self.borrowed_method_params
+                                            .entry(name.clone())
+                                            .and_modify(|previous| {
+                                                for (previous, borrowed) in
+                                                    previous.iter_mut().zip(&borrowed)
+                                                {
+                                                    *previous &= borrowed;
+                                                }
+                                            })
+                                            .or_insert(borrowed);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
                     for ast in function_define_s {
+                        self.definition_start = self.source_buf.len();
                         self.generator(ast);
                     }
                     if !self.is_sucsess_type_test {
-                        exit(0);
+                        return;
+                    }
+                    // 定義より前で呼んだ関数は、呼んだ定義の前にプロトタイプを置く
+                    let mut prototypes = std::mem::take(&mut self.prototypes);
+                    prototypes.sort_by_key(|(offset, _)| *offset);
+                    for (offset, prototype) in prototypes.into_iter().rev() {
+                        self.source_buf.insert_str(offset, &prototype);
+                    }
+                    match self.role {
+                        Role::Program => {
+                            for include in &self.includes {
+                                println!("{}", include);
+                            }
+                            if !self.includes.is_empty() {
+                                println!();
+                            }
+                            print!("{}", self.helpers);
+                            println!("{}", self.source_buf);
+                        }
+                        Role::Module => {
+                            // 宣言は `<名前>.h`、定義は `<名前>.c` に出力する
+                            let stem = match self.filename.rfind('.') {
+                                Some(index) => self.filename[..index].to_string(),
+                                None => self.filename.clone(),
+                            };
+                            let name = stem.rsplit('/').next().unwrap_or_default().to_string();
+                            let guard = format!("TF_{}_H", name.to_uppercase());
+                            let mut header = format!("#ifndef {0}\n#define {0}\n\n", guard);
+                            for include in &self.includes {
+                                header += &format!("{}\n", include);
+                            }
+                            if !self.includes.is_empty() {
+                                header += "\n";
+                            }
+                            header += &format!("{}\n#endif\n", self.header_buf);
+                            let source = format!(
+                                "#include \"{}.h\"\n\n{}{}",
+                                name, self.helpers, self.source_buf
+                            );
+                            self.write_output(format!("{}.h", stem), header);
+                            self.write_output(format!("{}.c", stem), source);
+                        }
+                        Role::Bundled => {}
                     }
-                    println!("{}", self.source_buf);
                 }
                 _ => {}
-            },
-            None => {}
+            }
         }
     }
 }
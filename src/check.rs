@@ -0,0 +1,745 @@
+use crate::constant::{self, Constant, EvalError};
+use crate::diagnostic::{self, Diagnostic};
+use crate::parse::{Node, NodeKind, CONSTRUCTOR_NAME};
+use crate::token::{Span, Type};
+use std::collections::{HashMap, HashSet};
+
+// 関数の引数 (名前と初期値があるか) と定義の場所 (import したものは None)
+#[derive(Clone)]
+struct Signature {
+    params: Vec<(String, bool)>,
+    span: Option<Span>,
+}
+
+impl Signature {
+    fn new(params: &[Node], span: Span) -> Self {
+        let params = params
+            .iter()
+            .filter_map(|param| match &param.kind {
+                Some(NodeKind::Param { name, default, .. }) => {
+                    Some((name.clone(), default.is_some()))
+                }
+                _ => None,
+            })
+            .collect();
+        Self {
+            params,
+            span: Some(span),
+        }
+    }
+
+    fn min(&self) -> usize {
+        self.params
+            .iter()
+            .filter(|(_, has_default)| !has_default)
+            .count()
+    }
+
+    fn max(&self) -> usize {
+        self.params.len()
+    }
+}
+
+// クラスの基底クラスと、そのクラスで定義されたフィールドとメソッド
+#[derive(Clone)]
+struct ClassInfo<'a> {
+    base: Option<String>,
+    fields: Vec<String>,
+    methods: Vec<&'a Node>,
+    // 他のファイルで定義されたクラスか
+    imported: bool,
+}
+
+// 定義の場所が同じファイルにあるときだけ指す
+fn with_definition(diagnostic: Diagnostic, span: Option<Span>) -> Diagnostic {
+    match span {
+        Some(span) => diagnostic.with_secondary_label(span, "label.defined_here", vec![]),
+        None => diagnostic,
+    }
+}
+
+// 基底クラスをたどってメンバーを探す
+fn has_member(classes: &HashMap<String, ClassInfo>, class_name: &str, name: &str) -> bool {
+    let mut class_name = Some(class_name);
+    while let Some(info) = class_name.and_then(|class_name| classes.get(class_name)) {
+        if info.fields.iter().any(|field| field == name)
+            || info
+                .methods
+                .iter()
+                .any(|method| method_name(method) == Some(name))
+        {
+            return true;
+        }
+        class_name = info.base.as_deref();
+    }
+    // コンストラクタは書かれていなくても作られる
+    name == CONSTRUCTOR_NAME
+}
+
+// 基底クラスをたどって同じ名前のメソッドを探す。定義したクラスが import したものかも返す
+fn find_method<'a>(
+    classes: &HashMap<String, ClassInfo<'a>>,
+    class_name: &str,
+    name: &str,
+) -> Option<(&'a Node, bool)> {
+    let mut class_name = Some(class_name);
+    while let Some(info) = class_name.and_then(|class_name| classes.get(class_name)) {
+        if let Some(method) = info
+            .methods
+            .iter()
+            .find(|method| method_name(method) == Some(name))
+        {
+            return Some((method, info.imported));
+        }
+        class_name = info.base.as_deref();
+    }
+    None
+}
+
+fn method_name(node: &Node) -> Option<&str> {
+    match &node.kind {
+        Some(NodeKind::Function {
+            function_name: Type::Identifier(name),
+            ..
+        }) => Some(name),
+        _ => None,
+    }
+}
+
+// 引数の名前と型、戻り値の型
+type MethodTypes<'a> = (Vec<(&'a String, &'a Option<String>)>, &'a Type);
+
+fn method_types(node: &Node) -> Option<MethodTypes<'_>> {
+    match &node.kind {
+        Some(NodeKind::Function {
+            params,
+            function_type,
+            ..
+        }) => Some((
+            params
+                .iter()
+                .filter_map(|param| match &param.kind {
+                    Some(NodeKind::Param { name, ty, .. }) => Some((name, ty)),
+                    _ => None,
+                })
+                .collect(),
+            function_type,
+        )),
+        _ => None,
+    }
+}
+
+// 構文解析の後、コードを生成する前に行う検査
+// import したファイルの定義 (関数、クラス、列挙型、定数) も使える。
+// imported は import されるものが先に並んでいること。aliases は別名で import した名前
+pub fn check(root: &Node, imported: &[&Node], aliases: &[(String, String)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut signatures = HashMap::new();
+    let mut classes = HashMap::new();
+    // 列挙型の名前と値
+    let mut enums = HashMap::new();
+    // 定数の名前と、コンパイル時に計算した値
+    let mut constants = HashMap::new();
+    let definitions = definitions_of(root);
+
+    // import したファイルのエラーはそのファイルで報告しているので、ここでは捨てる
+    for node in imported.iter().flat_map(|root| definitions_of(root)) {
+        collect_definition(
+            node,
+            &mut signatures,
+            &mut classes,
+            &mut enums,
+            &mut constants,
+            &mut Vec::new(),
+        );
+    }
+    // 他のファイルの定義の場所は、このファイルのソースの上では指せない
+    for signature in signatures.values_mut() {
+        signature.span = None;
+    }
+    for info in classes.values_mut() {
+        info.imported = true;
+    }
+    for (name, local) in aliases {
+        if let Some(signature) = signatures.get(name).cloned() {
+            signatures.insert(local.clone(), signature);
+        }
+        if let Some(info) = classes.get(name).cloned() {
+            classes.insert(local.clone(), info);
+        }
+        if let Some(variants) = enums.get(name).cloned() {
+            enums.insert(local.clone(), variants);
+        }
+        if let Some(value) = constants.get(name).cloned() {
+            constants.insert(local.clone(), value);
+        }
+    }
+    // 関数は呼び出しより後に定義されていてもよいので、先に全部集める
+    for node in definitions {
+        collect_definition(
+            node,
+            &mut signatures,
+            &mut classes,
+            &mut enums,
+            &mut constants,
+            &mut diagnostics,
+        );
+    }
+    check_arguments(root, &signatures, &mut diagnostics);
+    check_matches(root, &enums, &mut diagnostics);
+
+    let constants: HashSet<&String> = constants.keys().collect();
+    for node in definitions {
+        let functions = match &node.kind {
+            Some(NodeKind::Function { .. }) => vec![node],
+            Some(NodeKind::Class { menbers, .. }) => menbers.iter().collect(),
+            _ => Vec::new(),
+        };
+        for function in functions {
+            let locals = function.declared_names();
+            check_constant_assignments(function, &constants, &locals, &mut diagnostics);
+        }
+    }
+
+    for node in definitions {
+        match &node.kind {
+            Some(NodeKind::Function { .. }) => {
+                check_members(node, &classes, &mut HashMap::new(), &mut diagnostics);
+            }
+            Some(NodeKind::Class {
+                class_name,
+                menbers,
+                ..
+            }) => {
+                for menber in menbers {
+                    let mut variables = HashMap::new();
+                    variables.insert("self".to_string(), class_name.clone());
+                    if let Some(base) = classes.get(class_name).and_then(|info| info.base.clone()) {
+                        variables.insert("super".to_string(), base);
+                    }
+                    check_members(menber, &classes, &mut variables, &mut diagnostics);
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+fn definitions_of(root: &Node) -> &[Node] {
+    match &root.kind {
+        Some(NodeKind::Root { function_define_s }) => function_define_s,
+        _ => &[],
+    }
+}
+
+fn collect_definition<'a>(
+    node: &'a Node,
+    signatures: &mut HashMap<String, Signature>,
+    classes: &mut HashMap<String, ClassInfo<'a>>,
+    enums: &mut HashMap<String, Vec<String>>,
+    constants: &mut HashMap<String, Constant>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match &node.kind {
+        Some(NodeKind::Function {
+            params,
+            function_name: Type::Identifier(name),
+            ..
+        }) => {
+            signatures.insert(name.clone(), Signature::new(params, node.span));
+        }
+        Some(NodeKind::Global {
+            declaration,
+            is_const: true,
+        }) => {
+            if let Some(NodeKind::Let {
+                v_name,
+                v_formula,
+                this_is_define,
+                ..
+            }) = &declaration.kind
+            {
+                let value = if *this_is_define {
+                    constant::evaluate(v_formula, constants)
+                } else {
+                    Err(EvalError::NotConstant(declaration.span))
+                };
+                let label = match value {
+                    Ok(value) => {
+                        constants.insert(v_name.clone(), value);
+                        None
+                    }
+                    Err(_) if !this_is_define => Some((declaration.span, "label.missing_value")),
+                    Err(EvalError::NotConstant(span)) => Some((span, "label.not_constant")),
+                    Err(EvalError::DivisionByZero(span)) => Some((span, "label.division_by_zero")),
+                };
+                if let Some((span, label)) = label {
+                    diagnostics.push(
+                        Diagnostic::error(diagnostic::CONSTANT_NOT_EVALUABLE, vec![v_name.clone()])
+                            .with_label(span, label, vec![]),
+                    );
+                    // 後の定数で同じエラーが続かないように、名前だけは登録しておく
+                    constants.insert(v_name.clone(), Constant::Int(0));
+                }
+            }
+        }
+        Some(NodeKind::Enum { name, variants, .. }) => {
+            let variants: Vec<String> = variants
+                .iter()
+                .map(|(variant, _)| variant.clone())
+                .collect();
+            enums.insert(name.clone(), variants);
+        }
+        Some(NodeKind::Class {
+            class_name,
+            base,
+            fields,
+            menbers,
+            ..
+        }) => {
+            // 基底クラスはこのクラスより前に定義されていなければならない
+            let base = match base {
+                Some((base, _)) if classes.contains_key(base) => Some(base.clone()),
+                Some((base, span)) => {
+                    diagnostics.push(
+                        Diagnostic::error(diagnostic::UNKNOWN_BASE_CLASS, vec![base.clone()])
+                            .with_label(*span, "label.unknown_base_class", vec![]),
+                    );
+                    None
+                }
+                None => None,
+            };
+            let fields = fields
+                .iter()
+                .filter_map(|field| match &field.kind {
+                    Some(NodeKind::Let { v_name, .. }) => Some(v_name.clone()),
+                    _ => None,
+                })
+                .collect();
+            let methods: Vec<&Node> = menbers
+                .iter()
+                .filter(|menber| method_name(menber).is_some())
+                .collect();
+
+            // `Main(3)` はコンストラクタの呼び出し。なければ基底クラスのものを使う
+            let constructor = methods
+                .iter()
+                .find(|method| method_name(method) == Some(CONSTRUCTOR_NAME))
+                .and_then(|method| match &method.kind {
+                    Some(NodeKind::Function { params, .. }) => {
+                        Some(Signature::new(params, method.span))
+                    }
+                    _ => None,
+                })
+                .or_else(|| base.as_ref().and_then(|base| signatures.get(base).cloned()))
+                .unwrap_or_else(|| Signature::new(&[], node.span));
+            signatures.insert(class_name.clone(), constructor);
+
+            if let Some(base) = &base {
+                check_overrides(&methods, classes, base, diagnostics);
+            }
+            classes.insert(
+                class_name.clone(),
+                ClassInfo {
+                    base,
+                    fields,
+                    methods,
+                    imported: false,
+                },
+            );
+        }
+        _ => {}
+    }
+}
+
+// 基底クラスのメソッドを上書きするときは、引数と戻り値の型を同じにする
+fn check_overrides(
+    methods: &[&Node],
+    classes: &HashMap<String, ClassInfo>,
+    base: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for method in methods {
+        let Some(name) = method_name(method) else {
+            continue;
+        };
+        if name == CONSTRUCTOR_NAME {
+            continue;
+        }
+        if let Some((overridden, imported)) = find_method(classes, base, name) {
+            if method_types(method) != method_types(overridden) {
+                let diagnostic = Diagnostic::error(
+                    diagnostic::OVERRIDE_MISMATCH,
+                    vec![name.to_string(), base.to_string()],
+                )
+                .with_label(method.span, "label.override_mismatch", vec![]);
+                let span = (!imported).then_some(overridden.span);
+                diagnostics.push(with_definition(diagnostic, span));
+            }
+        }
+    }
+}
+
+// `a.x` / `a.f()` の a がクラスの変数なら、x や f がそのクラスにあるか調べる
+// variables は変数名とクラス名 (関数の中で宣言された順に増える)
+fn check_members(
+    node: &Node,
+    classes: &HashMap<String, ClassInfo>,
+    variables: &mut HashMap<String, String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match &node.kind {
+        Some(
+            NodeKind::Let {
+                v_name: name,
+                v_type: ty,
+                ..
+            }
+            | NodeKind::Param {
+                name, ty: Some(ty), ..
+            },
+        ) if classes.contains_key(ty) => {
+            variables.insert(name.clone(), ty.clone());
+        }
+        Some(NodeKind::Field { target, name } | NodeKind::MethodCall { target, name, .. }) => {
+            if let Some(NodeKind::Str(variable)) = &target.kind {
+                if variable == "super" && !variables.contains_key(variable) {
+                    diagnostics.push(
+                        Diagnostic::error(diagnostic::SUPER_WITHOUT_BASE, vec![]).with_label(
+                            target.span,
+                            "label.super_without_base",
+                            vec![],
+                        ),
+                    );
+                } else if let Some(class_name) = variables.get(variable) {
+                    if let (
+                        Some(NodeKind::MethodCall { args, kwargs, .. }),
+                        Some((method, imported)),
+                    ) = (&node.kind, find_method(classes, class_name, name))
+                    {
+                        // 引数の数とキーワード引数の名前をメソッドの定義と比べる
+                        if let Some(NodeKind::Function { params, .. }) = &method.kind {
+                            let mut signature = Signature::new(params, method.span);
+                            if imported {
+                                signature.span = None;
+                            }
+                            let method_name = format!("{}.{}", class_name, name);
+                            check_call(node, &method_name, args, kwargs, &signature, diagnostics);
+                        }
+                    } else if !has_member(classes, class_name, name) {
+                        diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::UNKNOWN_MEMBER,
+                                vec![class_name.clone(), name.clone()],
+                            )
+                            .with_label(
+                                node.span,
+                                "label.unknown_member",
+                                vec![],
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        check_members(child, classes, variables, diagnostics);
+    }
+}
+
+// `State.Idle` の値が列挙型にあるか、match が列挙型の値をすべて扱っているか
+fn check_matches(
+    node: &Node,
+    enums: &HashMap<String, Vec<String>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match &node.kind {
+        Some(NodeKind::Field { target, name }) => {
+            if let Some(NodeKind::Str(enum_name)) = &target.kind {
+                if enums
+                    .get(enum_name)
+                    .is_some_and(|variants| !variants.contains(name))
+                {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            diagnostic::UNKNOWN_VARIANT,
+                            vec![enum_name.clone(), name.clone()],
+                        )
+                        .with_label(
+                            node.span,
+                            "label.unknown_variant",
+                            vec![],
+                        ),
+                    );
+                }
+            }
+        }
+        Some(NodeKind::Match {
+            value,
+            arms,
+            default: None,
+        }) => {
+            let patterns: Vec<&Node> = arms
+                .iter()
+                .flat_map(|arm| match &arm.kind {
+                    Some(NodeKind::MatchArm { patterns, .. }) => patterns.iter().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+            // パターンがすべて同じ列挙型の値なら、足りない値を挙げる
+            let variants: Vec<(&String, &String)> = patterns
+                .iter()
+                .filter_map(|pattern| match &pattern.kind {
+                    Some(NodeKind::Field { target, name }) => match &target.kind {
+                        Some(NodeKind::Str(enum_name)) if enums.contains_key(enum_name) => {
+                            Some((enum_name, name))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect();
+            let enum_name = variants.first().map(|(enum_name, _)| *enum_name);
+            let label = match enum_name {
+                Some(enum_name)
+                    if variants.len() == patterns.len()
+                        && variants.iter().all(|(name, _)| *name == enum_name) =>
+                {
+                    let missing: Vec<String> = enums[enum_name]
+                        .iter()
+                        .filter(|variant| !variants.iter().any(|(_, name)| name == variant))
+                        .map(|variant| format!("`{}.{}`", enum_name, variant))
+                        .collect();
+                    (!missing.is_empty())
+                        .then(|| ("label.missing_variants", vec![missing.join(", ")]))
+                }
+                _ => Some(("label.missing_else", vec![])),
+            };
+            if let Some((label, args)) = label {
+                diagnostics.push(
+                    Diagnostic::error(diagnostic::NON_EXHAUSTIVE_MATCH, vec![])
+                        .with_label(value.span, label, args),
+                );
+            }
+        }
+        _ => {}
+    }
+    for child in node.children() {
+        check_matches(child, enums, diagnostics);
+    }
+}
+
+// 同じ名前の変数を関数の中で宣言していなければ、定数には代入できない
+fn check_constant_assignments(
+    node: &Node,
+    constants: &HashSet<&String>,
+    locals: &HashSet<&String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(NodeKind::Assign { lhs, .. }) = &node.kind {
+        if let Some(NodeKind::Str(name)) = &lhs.kind {
+            if constants.contains(name) && !locals.contains(name) {
+                diagnostics.push(
+                    Diagnostic::error(diagnostic::ASSIGN_TO_CONSTANT, vec![name.clone()])
+                        .with_label(node.span, "label.assign_to_constant", vec![]),
+                );
+            }
+        }
+    }
+    for child in node.children() {
+        check_constant_assignments(child, constants, locals, diagnostics);
+    }
+}
+
+fn check_arguments(
+    node: &Node,
+    signatures: &HashMap<String, Signature>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // `a.run(1)` は MethodCall なので、同じ名前の関数とは比べない
+    if let Some(NodeKind::Call {
+        function_name,
+        args,
+        kwargs,
+    }) = &node.kind
+    {
+        if let Some(signature) = signatures.get(function_name) {
+            check_call(node, function_name, args, kwargs, signature, diagnostics);
+        }
+    }
+    for child in node.children() {
+        check_arguments(child, signatures, diagnostics);
+    }
+}
+
+// 呼び出しの引数が関数の定義と合っているか
+fn check_call(
+    node: &Node,
+    function_name: &str,
+    args: &[Node],
+    kwargs: &[(String, Node)],
+    signature: &Signature,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (min, max) = (signature.min(), signature.max());
+    let mismatch = || {
+        Diagnostic::error(
+            diagnostic::ARGUMENT_COUNT_MISMATCH,
+            vec![function_name.to_string()],
+        )
+    };
+
+    if args.len() > max || (kwargs.is_empty() && args.len() < min) {
+        let found = (args.len() + kwargs.len()).to_string();
+        let diagnostic = if min == max {
+            mismatch().with_label(
+                node.span,
+                "label.expected_args",
+                vec![max.to_string(), found],
+            )
+        } else {
+            mismatch().with_label(
+                node.span,
+                "label.expected_args_range",
+                vec![min.to_string(), max.to_string(), found],
+            )
+        };
+        diagnostics.push(with_definition(diagnostic, signature.span));
+        return;
+    }
+
+    let mut given: Vec<&str> = signature
+        .params
+        .iter()
+        .take(args.len())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    for (name, value) in kwargs {
+        if !signature.params.iter().any(|(param, _)| param == name) {
+            let diagnostic = Diagnostic::error(
+                diagnostic::UNKNOWN_KEYWORD_ARGUMENT,
+                vec![function_name.to_string(), name.clone()],
+            )
+            .with_label(value.span, "label.unknown_keyword", vec![]);
+            diagnostics.push(with_definition(diagnostic, signature.span));
+        } else if given.contains(&name.as_str()) {
+            diagnostics.push(
+                Diagnostic::error(diagnostic::DUPLICATE_ARGUMENT, vec![name.clone()]).with_label(
+                    value.span,
+                    "label.duplicate_argument",
+                    vec![],
+                ),
+            );
+        } else {
+            given.push(name);
+        }
+    }
+
+    for (param, has_default) in &signature.params {
+        if !has_default && !given.contains(&param.as_str()) {
+            diagnostics.push(with_definition(
+                mismatch().with_label(node.span, "label.missing_argument", vec![param.clone()]),
+                signature.span,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::token::Lexer;
+
+    // ソースを読んで検査し、見つかったエラーのコードと行を返す
+    fn errors(code: &str) -> Vec<(&'static str, usize)> {
+        let tokens = Lexer::new(code.to_string()).lex();
+        let root = match Parser::new(&tokens).root() {
+            Ok(root) => root,
+            Err(errors) => {
+                let codes: Vec<&str> = errors.iter().map(|error| error.code).collect();
+                panic!("unexpected parse errors {:?} in {:?}", codes, code)
+            }
+        };
+        check(&root, &[], &[])
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.labels[0].span.line))
+            .collect()
+    }
+
+    #[test]
+    fn argument_counts_follow_defaults() {
+        let code = "int: add(int: a, int: b <- 0) <- {\n    return a + b;\n}\nint: main <- {\n    add(1);\n    add(1, 2);\n    add();\n    add(1, 2, 3);\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![
+                (diagnostic::ARGUMENT_COUNT_MISMATCH, 7),
+                (diagnostic::ARGUMENT_COUNT_MISMATCH, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn keyword_arguments_match_param_names() {
+        let code = "int: run(int: speed, int: angle <- 0, bool: wait <- true) <- {\n    return speed;\n}\nint: main <- {\n    run(100, wait = false);\n    run(angle = 90, speed = 100);\n    run(100, turn = 1);\n    run(100, speed = 1);\n    run(wait = true);\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![
+                (diagnostic::UNKNOWN_KEYWORD_ARGUMENT, 7),
+                (diagnostic::DUPLICATE_ARGUMENT, 8),
+                (diagnostic::ARGUMENT_COUNT_MISMATCH, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn members_are_looked_up_on_the_class() {
+        let code = "class Wheel <- {\n    int: size <- 1;\n\n    int: grow(self, int: n) <- {\n        self.size <- self.size + n;\n        return self.width;\n    }\n}\nint: main <- {\n    Wheel: w <- Wheel();\n    print(w.size);\n    print(w.radius);\n    w.grow(1);\n    w.shrink();\n    w.grow();\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![
+                (diagnostic::UNKNOWN_MEMBER, 6),
+                (diagnostic::UNKNOWN_MEMBER, 12),
+                (diagnostic::UNKNOWN_MEMBER, 14),
+                (diagnostic::ARGUMENT_COUNT_MISMATCH, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn constructor_arguments_are_checked() {
+        let code = "class Main <- {\n    int: x;\n\n    void: _init_(self, int: x) <- {\n        self.x <- x;\n    }\n}\nint: main <- {\n    Main: a <- Main(3);\n    Main: b <- Main();\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![(diagnostic::ARGUMENT_COUNT_MISMATCH, 10)]
+        );
+    }
+
+    #[test]
+    fn overrides_match_the_base_class() {
+        let code = "class Base <- {\n    int: speed <- 1;\n\n    int: go(self, int: by) <- {\n        return by;\n    }\n}\nclass Fast : Base <- {\n    int: go(self, int: by) <- {\n        return super.go(by) + self.speed;\n    }\n}\nclass Slow : Base <- {\n    float: go(self, int: by) <- {\n        return 0.5;\n    }\n}\nclass Lost : Missing <- {\n}\nclass Alone <- {\n    int: go(self) <- {\n        return super.go();\n    }\n}\nint: main <- {\n    Fast: f <- Fast();\n    print(f.speed + f.go(1));\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![
+                (diagnostic::OVERRIDE_MISMATCH, 14),
+                (diagnostic::UNKNOWN_BASE_CLASS, 18),
+                (diagnostic::SUPER_WITHOUT_BASE, 22),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_cover_every_variant() {
+        let code = "enum State <- { Idle, Driving, Turning }\nint: main <- {\n    State: s <- State.Idle;\n    match s <- {\n        State.Idle <- {\n        }\n    }\n    match s <- {\n        State.Idle, State.Driving <- {\n        }\n        State.Turning <- {\n        }\n    }\n    match s <- {\n        State.Idle <- {\n        }\n        else <- {\n        }\n    }\n    s <- State.Flying;\n    return 0;\n}\n";
+        assert_eq!(
+            errors(code),
+            vec![
+                (diagnostic::NON_EXHAUSTIVE_MATCH, 4),
+                (diagnostic::UNKNOWN_VARIANT, 20),
+            ]
+        );
+    }
+}
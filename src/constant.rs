@@ -0,0 +1,254 @@
+use crate::parse::{Node, NodeKind};
+use crate::token::{Span, Type};
+use std::collections::HashMap;
+
+// コンパイル時に決まる値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i32),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+// 評価できなかった理由と場所
+pub enum EvalError {
+    NotConstant(Span),
+    DivisionByZero(Span),
+}
+
+impl Constant {
+    pub fn to_node(&self, span: Span) -> Node {
+        let kind = match self {
+            Constant::Int(number) => NodeKind::Num(*number),
+            Constant::Float(number) => NodeKind::Float(*number),
+            Constant::Str(text) => NodeKind::StrLiteral(text.clone()),
+            Constant::Bool(value) => NodeKind::Bool(*value),
+        };
+        Node {
+            kind: Some(kind),
+            span,
+        }
+    }
+}
+
+// 定数式を評価する。名前は constants に入っている定数だけ使える
+pub fn evaluate(node: &Node, constants: &HashMap<String, Constant>) -> Result<Constant, EvalError> {
+    let not_constant = || EvalError::NotConstant(node.span);
+    match &node.kind {
+        Some(NodeKind::Num(number)) => Ok(Constant::Int(*number)),
+        Some(NodeKind::Float(number)) => Ok(Constant::Float(*number)),
+        Some(NodeKind::StrLiteral(text)) => Ok(Constant::Str(text.clone())),
+        Some(NodeKind::Bool(value)) => Ok(Constant::Bool(*value)),
+        Some(NodeKind::Str(name)) => constants.get(name).cloned().ok_or_else(not_constant),
+        Some(NodeKind::UnaryOp {
+            op: Type::Minus,
+            operand,
+        }) => match evaluate(operand, constants)? {
+            Constant::Int(number) => number
+                .checked_neg()
+                .map(Constant::Int)
+                .ok_or_else(not_constant),
+            Constant::Float(number) => Ok(Constant::Float(-number)),
+            Constant::Str(_) | Constant::Bool(_) => Err(not_constant()),
+        },
+        Some(NodeKind::UnaryOp {
+            op: Type::Not,
+            operand,
+        }) => match evaluate(operand, constants)? {
+            Constant::Bool(value) => Ok(Constant::Bool(!value)),
+            _ => Err(not_constant()),
+        },
+        Some(NodeKind::Logical { lhs, op, rhs }) => {
+            match (evaluate(lhs, constants)?, evaluate(rhs, constants)?) {
+                (Constant::Bool(lhs), Constant::Bool(rhs)) => match op {
+                    Type::And => Ok(Constant::Bool(lhs && rhs)),
+                    Type::Or => Ok(Constant::Bool(lhs || rhs)),
+                    _ => Err(not_constant()),
+                },
+                _ => Err(not_constant()),
+            }
+        }
+        Some(NodeKind::Compare { lhs, op, rhs }) => {
+            let ordering = match (evaluate(lhs, constants)?, evaluate(rhs, constants)?) {
+                (Constant::Int(lhs), Constant::Int(rhs)) => Some(lhs.cmp(&rhs)),
+                (
+                    lhs @ (Constant::Int(_) | Constant::Float(_)),
+                    rhs @ (Constant::Int(_) | Constant::Float(_)),
+                ) => {
+                    let as_float = |value| match value {
+                        Constant::Int(number) => number as f64,
+                        Constant::Float(number) => number,
+                        Constant::Str(_) | Constant::Bool(_) => 0.0,
+                    };
+                    as_float(lhs).partial_cmp(&as_float(rhs))
+                }
+                // 文字列と bool は C で大小を比べられないので、等しいかどうかだけ
+                (Constant::Str(lhs), Constant::Str(rhs))
+                    if matches!(**op, Type::EqualEqual | Type::NotEqual) =>
+                {
+                    Some(lhs.cmp(&rhs))
+                }
+                (Constant::Bool(lhs), Constant::Bool(rhs))
+                    if matches!(**op, Type::EqualEqual | Type::NotEqual) =>
+                {
+                    Some(lhs.cmp(&rhs))
+                }
+                _ => return Err(not_constant()),
+            };
+            // NaN はどの比較も偽 (!= だけ真) になる
+            let Some(ordering) = ordering else {
+                return Ok(Constant::Bool(**op == Type::NotEqual));
+            };
+            match **op {
+                Type::Less => Ok(Constant::Bool(ordering.is_lt())),
+                Type::Greater => Ok(Constant::Bool(ordering.is_gt())),
+                Type::LessEqual => Ok(Constant::Bool(ordering.is_le())),
+                Type::GreaterEqual => Ok(Constant::Bool(ordering.is_ge())),
+                Type::EqualEqual => Ok(Constant::Bool(ordering.is_eq())),
+                Type::NotEqual => Ok(Constant::Bool(ordering.is_ne())),
+                _ => Err(not_constant()),
+            }
+        }
+        Some(NodeKind::BinaryOp { op, lhs, rhs }) => {
+            let lhs = evaluate(lhs, constants)?;
+            let rhs = evaluate(rhs, constants)?;
+            match (lhs, rhs) {
+                // 整数どうしの割り算は C と同じく切り捨てる
+                (Constant::Int(lhs), Constant::Int(rhs)) => match op {
+                    Type::Plus => lhs.checked_add(rhs),
+                    Type::Minus => lhs.checked_sub(rhs),
+                    Type::Asterisk => lhs.checked_mul(rhs),
+                    Type::Slash if rhs == 0 => return Err(EvalError::DivisionByZero(node.span)),
+                    Type::Slash => lhs.checked_div(rhs),
+                    _ => None,
+                }
+                .map(Constant::Int)
+                .ok_or_else(not_constant),
+                (Constant::Str(lhs), Constant::Str(rhs)) if *op == Type::Plus => {
+                    Ok(Constant::Str(lhs + &rhs))
+                }
+                (Constant::Str(_) | Constant::Bool(_), _)
+                | (_, Constant::Str(_) | Constant::Bool(_)) => Err(not_constant()),
+                (lhs, rhs) => {
+                    let as_float = |value| match value {
+                        Constant::Int(number) => number as f64,
+                        Constant::Float(number) => number,
+                        Constant::Str(_) | Constant::Bool(_) => 0.0,
+                    };
+                    let (lhs, rhs) = (as_float(lhs), as_float(rhs));
+                    match op {
+                        Type::Plus => Ok(Constant::Float(lhs + rhs)),
+                        Type::Minus => Ok(Constant::Float(lhs - rhs)),
+                        Type::Asterisk => Ok(Constant::Float(lhs * rhs)),
+                        Type::Slash if rhs == 0.0 => Err(EvalError::DivisionByZero(node.span)),
+                        Type::Slash => Ok(Constant::Float(lhs / rhs)),
+                        _ => Err(not_constant()),
+                    }
+                }
+            }
+        }
+        _ => Err(not_constant()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::token::Lexer;
+
+    // `const` の並びを読み、順に評価した結果を返す (前の定数は後の式で使える)
+    fn evaluate_all(code: &str) -> Vec<Result<Constant, EvalError>> {
+        let tokens = Lexer::new(code.to_string()).lex();
+        let Ok(Node {
+            kind: Some(NodeKind::Root { function_define_s }),
+            ..
+        }) = Parser::new(&tokens).root()
+        else {
+            panic!("failed to parse {:?}", code);
+        };
+        let mut constants = HashMap::new();
+        function_define_s
+            .iter()
+            .map(|global| {
+                let Some(NodeKind::Global { declaration, .. }) = &global.kind else {
+                    panic!("expected a global");
+                };
+                let Some(NodeKind::Let {
+                    v_name, v_formula, ..
+                }) = &declaration.kind
+                else {
+                    panic!("expected a declaration");
+                };
+                let value = evaluate(v_formula, &constants);
+                if let Ok(value) = &value {
+                    constants.insert(v_name.clone(), value.clone());
+                }
+                value
+            })
+            .collect()
+    }
+
+    fn value(code: &str) -> Constant {
+        match evaluate_all(code).pop() {
+            Some(Ok(value)) => value,
+            _ => panic!("failed to evaluate {:?}", code),
+        }
+    }
+
+    #[test]
+    fn arithmetic_follows_c() {
+        assert_eq!(value("const int: A <- (7 + 1) / 2 * 3;"), Constant::Int(12));
+        assert_eq!(value("const int: A <- -7 / 2;"), Constant::Int(-3));
+        assert_eq!(value("const float: A <- 7 / 2.0;"), Constant::Float(3.5));
+        assert_eq!(
+            value("const string: A <- \"tf\" + \"!\";"),
+            Constant::Str("tf!".to_string())
+        );
+        assert_eq!(
+            value("const int: A <- 2;\nconst int: B <- A * A;"),
+            Constant::Int(4)
+        );
+    }
+
+    #[test]
+    fn not_compare_and_logical() {
+        assert_eq!(value("const bool: V <- not false;"), Constant::Bool(true));
+        assert_eq!(value("const bool: W <- 1 < 2;"), Constant::Bool(true));
+        assert_eq!(value("const bool: W <- 2 <= 1.5;"), Constant::Bool(false));
+        assert_eq!(
+            value("const bool: W <- \"a\" != \"b\" and true == true;"),
+            Constant::Bool(true)
+        );
+        assert_eq!(
+            value("const bool: V <- false;\nconst bool: W <- V or not V;"),
+            Constant::Bool(true)
+        );
+    }
+
+    #[test]
+    fn operands_of_the_wrong_type_are_not_constant() {
+        for code in [
+            "const bool: A <- not 1;",
+            "const bool: A <- 1 and true;",
+            "const bool: A <- 1 < \"a\";",
+            "const bool: A <- \"a\" < \"b\";",
+            "const int: A <- true + 1;",
+            "const int: A <- f();",
+        ] {
+            assert!(
+                matches!(
+                    evaluate_all(code).pop(),
+                    Some(Err(EvalError::NotConstant(_)))
+                ),
+                "{:?} should not be constant",
+                code
+            );
+        }
+        assert!(matches!(
+            evaluate_all("const int: A <- 1 / 0;").pop(),
+            Some(Err(EvalError::DivisionByZero(_)))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::message::{message, Lang, Message};
+use crate::token::Span;
+
+// エラーコード (一度割り当てた番号は変えない)
+pub const UNEXPECTED_TOKEN: &str = "TF0001";
+pub const EXPECTED_EXPRESSION: &str = "TF0002";
+pub const EXPECTED_IDENTIFIER: &str = "TF0003";
+pub const INVALID_CHARACTER: &str = "TF0004";
+pub const INVALID_NUMBER: &str = "TF0005";
+pub const UNTERMINATED_STRING: &str = "TF0006";
+pub const UNKNOWN_ESCAPE: &str = "TF0007";
+pub const UNCLOSED_INTERPOLATION: &str = "TF0008";
+pub const UNTERMINATED_COMMENT: &str = "TF0009";
+pub const INVALID_ASSIGN_TARGET: &str = "TF0010";
+pub const DEFAULT_PARAM_ORDER: &str = "TF0011";
+pub const POSITIONAL_AFTER_KEYWORD: &str = "TF0012";
+pub const UNDEFINED_NAME: &str = "TF0101";
+pub const JUMP_OUTSIDE_LOOP: &str = "TF0102";
+pub const UNDECLARED_LABEL: &str = "TF0103";
+pub const ARGUMENT_COUNT_MISMATCH: &str = "TF0104";
+pub const UNKNOWN_KEYWORD_ARGUMENT: &str = "TF0105";
+pub const DUPLICATE_ARGUMENT: &str = "TF0106";
+pub const UNKNOWN_MEMBER: &str = "TF0107";
+pub const UNKNOWN_BASE_CLASS: &str = "TF0108";
+pub const OVERRIDE_MISMATCH: &str = "TF0109";
+pub const SUPER_WITHOUT_BASE: &str = "TF0110";
+pub const UNKNOWN_VARIANT: &str = "TF0111";
+pub const NON_EXHAUSTIVE_MATCH: &str = "TF0112";
+pub const CONSTANT_NOT_EVALUABLE: &str = "TF0113";
+pub const ASSIGN_TO_CONSTANT: &str = "TF0114";
+pub const IMPORT_CYCLE: &str = "TF0115";
+pub const UNKNOWN_IMPORT: &str = "TF0116";
+pub const SHADOWED_NAME: &str = "TF0117";
+pub const DUPLICATE_DEFINITION: &str = "TF0118";
+pub const TYPE_MISMATCH: &str = "TF0119";
+pub const INVALID_OPERANDS: &str = "TF0120";
+pub const UNKNOWN_TYPE: &str = "TF0121";
+pub const UNSUPPORTED_BY_TARGET: &str = "TF0201";
+pub const MAP_CAPACITY_EXCEEDED: &str = "TF0202";
+pub const OUTPUT_WRITE_FAILED: &str = "TF0301";
+pub const MODULE_READ_FAILED: &str = "TF0302";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: Message,
+    pub primary: bool,
+}
+
+// コンパイル中に見つかったエラーや警告
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub args: Vec<String>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<Message>,
+}
+
+impl Diagnostic {
+    // メッセージ本文はエラーコードをキーにカタログから引く
+    pub fn new(severity: Severity, code: &'static str, args: Vec<String>) -> Self {
+        Self {
+            severity,
+            code,
+            args,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: &'static str, args: Vec<String>) -> Self {
+        Self::new(Severity::Error, code, args)
+    }
+
+    pub fn warning(code: &'static str, args: Vec<String>) -> Self {
+        Self::new(Severity::Warning, code, args)
+    }
+
+    // エラーの原因となった場所
+    pub fn with_label(mut self, span: Span, key: &'static str, args: Vec<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: Message::new(key, args),
+            primary: true,
+        });
+        self
+    }
+
+    // 原因に関連する場所
+    pub fn with_secondary_label(
+        mut self,
+        span: Span,
+        key: &'static str,
+        args: Vec<String>,
+    ) -> Self {
+        self.labels.push(Label {
+            span,
+            message: Message::new(key, args),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, key: &'static str, args: Vec<String>) -> Self {
+        self.notes.push(Message::new(key, args));
+        self
+    }
+
+    pub fn message(&self, lang: Lang) -> String {
+        Message::new(self.code, self.args.clone()).render(lang)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .map(|label| label.span)
+    }
+}
+
+// 端末上での文字幅 (全角文字は 2)
+fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+fn str_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+// エラーを `--> file:line:col` とソース行、`^^^` 付きの文字列にする
+pub fn render(diagnostic: &Diagnostic, source: &str, filename: &str, lang: Lang) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "severity.error",
+        Severity::Warning => "severity.warning",
+    };
+    let mut out = format!(
+        "{}[{}]: {}\n",
+        message(lang, severity, &[]),
+        diagnostic.code,
+        diagnostic.message(lang)
+    );
+
+    let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+    labels.sort_by_key(|label| (label.span.start, !label.primary));
+
+    let gutter = labels
+        .iter()
+        .map(|label| label.span.line.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let pad = " ".repeat(gutter);
+
+    match diagnostic
+        .primary_span()
+        .or(labels.first().map(|label| label.span))
+    {
+        Some(span) => out += &format!("{}--> {}:{}:{}\n", pad, filename, span.line, span.column),
+        None => out += &format!("{}--> {}\n", pad, filename),
+    }
+
+    if !labels.is_empty() {
+        out += &format!("{} |\n", pad);
+    }
+    let mut last_line = 0;
+    for label in labels {
+        let start = label.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line_text = source[line_start..line_end].trim_end_matches('\r');
+        let end = label.span.end.clamp(start, line_end);
+
+        if label.span.line != last_line {
+            out += &format!(
+                "{:>width$} | {}\n",
+                label.span.line,
+                line_text,
+                width = gutter
+            );
+            last_line = label.span.line;
+        }
+        let marker = if label.primary { "^" } else { "-" };
+        let offset = str_width(&source[line_start..start]);
+        let length = str_width(&source[start..end]).max(1);
+        out += &format!(
+            "{} | {}{} {}\n",
+            pad,
+            " ".repeat(offset),
+            marker.repeat(length),
+            label.message.render(lang)
+        );
+    }
+
+    for note in &diagnostic.notes {
+        out += &format!(
+            "{} = {}: {}\n",
+            pad,
+            message(lang, "severity.note", &[]),
+            note.render(lang)
+        );
+    }
+    out
+}
+
+// 全ての診断を標準エラーに出力し、エラーが含まれていれば true を返す
+pub fn emit(diagnostics: &[Diagnostic], source: &str, filename: &str, lang: Lang) -> bool {
+    let mut sorted: Vec<&Diagnostic> = diagnostics.iter().collect();
+    sorted.sort_by_key(|diagnostic| {
+        diagnostic
+            .primary_span()
+            .map_or(usize::MAX, |span| span.start)
+    });
+
+    for diagnostic in sorted {
+        eprintln!("{}", render(diagnostic, source, filename, lang));
+    }
+    diagnostics.iter().any(Diagnostic::is_error)
+}
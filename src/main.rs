@@ -1,11 +1,36 @@
 mod c_generator;
+mod check;
+mod constant;
+mod diagnostic;
+mod message;
+mod module;
 mod parse;
 mod python_generator;
+mod resolve;
 mod token;
+mod typecheck;
 
+use c_generator::C_Generator;
+use diagnostic::{Diagnostic, Severity};
+use message::{message, Lang};
+use module::{Module, Role};
+use parse::Node;
+use python_generator::PythonGenerator;
+use resolve::Bindings;
 use std::env;
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use typecheck::Types;
+
+// コマンドラインで指定する出力の設定
+struct Options {
+    target: String,
+    // import するファイルを探すディレクトリ (import しているファイルのディレクトリの次に探す)
+    search_path: Vec<PathBuf>,
+    // import したファイルも含めて1つのファイルに出力する
+    bundle: bool,
+}
 
 fn read_from_file(filename: &str) -> io::Result<String> {
     let mut file = File::open(filename)?; // ファイルを開く
@@ -16,26 +41,216 @@ fn read_from_file(filename: &str) -> io::Result<String> {
     Ok(content) // 読み込んだテキストを`Ok`で返す
 }
 
-fn run(code_string: String, filename: String) {
-    let mut lexer = token::Lexer::new(code_string);
-    let tokens = lexer.lex(); // Token列を作成
-    let mut parse = parse::Parser::new(&tokens);
-    let ast = parse.root(); // AST列を作成
+// コマンドラインのエラーを表示して終了する
+fn fail(lang: Lang, key: &str, args: &[String]) -> ! {
+    eprintln!(
+        "{}: {}",
+        message(lang, "severity.error", &[]),
+        message(lang, key, args)
+    );
+    std::process::exit(1);
+}
 
-    //let mut generator = c_generator::C_Generator::new();
-    let mut generator = python_generator::PythonGenerator::new(filename); // Python generator
-                                                                          // のインスタンスを作成
-    generator.generator(ast) // AST列を解析
+// 生成で出たエラーを表示し、エラーがあれば終了する
+fn emit_or_exit(diagnostics: &[Diagnostic], module: &Module, lang: Lang) {
+    if diagnostic::emit(diagnostics, &module.source, &module.filename, lang) {
+        std::process::exit(1);
+    }
+}
+
+// types はファイルごとの式の型、bindings は名前と宣言の結びつき (modules と同じ順番)
+fn generate_python(
+    modules: &[Module],
+    mut types: Vec<Types>,
+    mut bindings: Vec<Bindings>,
+    bundle: bool,
+    lang: Lang,
+) -> Vec<String> {
+    let entry = modules.len() - 1;
+    if bundle {
+        // import されるものから順に1つのファイルに出力する
+        let mut generator = PythonGenerator::new(modules[entry].filename.clone());
+        for import in modules.iter().flat_map(|module| &module.imports) {
+            generator.bundle_module(&import.path);
+        }
+        for (index, module) in modules.iter().enumerate() {
+            generator.role = if index == entry {
+                Role::Program
+            } else {
+                Role::Bundled
+            };
+            generator.types = std::mem::take(&mut types[index]);
+            generator.bindings = std::mem::take(&mut bindings[index]);
+            generator.generator(module.ast.clone());
+            emit_or_exit(&std::mem::take(&mut generator.diagnostics), module, lang);
+        }
+        return generator.output_file.into_iter().collect();
+    }
+
+    let mut generators: Vec<PythonGenerator> = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        let mut generator = PythonGenerator::new(module.filename.clone());
+        if index != entry {
+            generator.role = Role::Module;
+        }
+        for import in &module.imports {
+            generator.import_module(import, &generators[import.module]);
+        }
+        generator.types = std::mem::take(&mut types[index]);
+        generator.bindings = std::mem::take(&mut bindings[index]);
+        generator.generator(module.ast.clone());
+        emit_or_exit(&generator.diagnostics, module, lang);
+        generators.push(generator);
+    }
+    generators
+        .into_iter()
+        .filter_map(|generator| generator.output_file)
+        .collect()
+}
+
+// bindings はファイルごとの名前と宣言の結びつき (modules と同じ順番)
+fn generate_c(
+    modules: &[Module],
+    mut types: Vec<Types>,
+    mut bindings: Vec<Bindings>,
+    bundle: bool,
+    lang: Lang,
+) -> Vec<String> {
+    let entry = modules.len() - 1;
+    if bundle {
+        let mut generator = C_Generator::new();
+        for module in modules {
+            generator.extend_classes(&module.ast);
+        }
+        for import in modules.iter().flat_map(|module| &module.imports) {
+            generator.bundle_module(&import.path);
+        }
+        for (index, module) in modules.iter().enumerate() {
+            generator.role = if index == entry {
+                Role::Program
+            } else {
+                Role::Bundled
+            };
+            generator.types = std::mem::take(&mut types[index]);
+            generator.bindings = std::mem::take(&mut bindings[index]);
+            generator.generator(module.ast.clone());
+            emit_or_exit(&std::mem::take(&mut generator.diagnostics), module, lang);
+        }
+        return Vec::new();
+    }
+
+    // 入口のファイルは標準出力に、import されるファイルは `.h` と `.c` に出力する
+    let mut generators: Vec<C_Generator> = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        let mut generator = if index == entry {
+            C_Generator::new()
+        } else {
+            C_Generator::new_module(module.filename.clone())
+        };
+        for module in modules {
+            generator.extend_classes(&module.ast);
+        }
+        for import in &module.imports {
+            generator.import_module(import, &generators[import.module]);
+        }
+        generator.types = std::mem::take(&mut types[index]);
+        generator.bindings = std::mem::take(&mut bindings[index]);
+        generator.generator(module.ast.clone());
+        emit_or_exit(&generator.diagnostics, module, lang);
+        generators.push(generator);
+    }
+    generators
+        .into_iter()
+        .flat_map(|generator| generator.output_files)
+        .collect()
+}
+
+fn run(code_string: String, filename: String, options: &Options, lang: Lang) {
+    // import されるファイルも読み込み、それぞれのファイルごとにエラーを表示する
+    let modules = module::load(filename, code_string, options.search_path.clone());
+    let mut failed = false;
+    for module in &modules {
+        failed |= diagnostic::emit(&module.diagnostics, &module.source, &module.filename, lang);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    // Python では `language` ブロックで定義した名前も使えるので、見つからない名前は警告にする
+    let undefined = if options.target == "c" {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    let mut types = Vec::new();
+    let mut bindings = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        let imported: Vec<&Node> = module::reachable(&modules, index)
+            .into_iter()
+            .map(|import| &modules[import].ast)
+            .collect();
+        let (mut diagnostics, module_bindings) =
+            resolve::resolve(&module.ast, &module.imports, undefined);
+        bindings.push(module_bindings);
+        diagnostics.extend(check::check(&module.ast, &imported, &module.aliases()));
+        let (type_errors, module_types) =
+            typecheck::type_check(&module.ast, &imported, &module.imports);
+        diagnostics.extend(type_errors);
+        types.push(module_types);
+        failed |= diagnostic::emit(&diagnostics, &module.source, &module.filename, lang);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+
+    let output_files = if options.target == "c" {
+        generate_c(&modules, types, bindings, options.bundle, lang)
+    } else {
+        generate_python(&modules, types, bindings, options.bundle, lang)
+    };
+    for output_file in output_files {
+        let written = message(lang, "cli.file_written", &[output_file]);
+        // C は生成したコードを標準出力に出すので、こちらは標準エラー出力にする
+        if options.target == "c" {
+            eprintln!("{}", written);
+        } else {
+            println!("{}", written);
+        }
+    }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 {
-        eprintln!("Err: 引数が足りません");
-    }
-    let filename = &args[1];
-    match read_from_file(filename) {
-        Ok(code_string) => run(code_string, filename.clone()),
-        Err(e) => eprintln!("Error: {}", e),
+    let mut filename = None;
+    let mut options = Options {
+        target: String::from("python"),
+        search_path: Vec::new(),
+        bundle: false,
+    };
+    let mut lang = Lang::from_env();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => options.target = args.next().unwrap_or_default(),
+            "--path" => options
+                .search_path
+                .push(PathBuf::from(args.next().unwrap_or_default())),
+            "--bundle" => options.bundle = true,
+            "--lang" => {
+                let name = args.next().unwrap_or_default();
+                lang =
+                    Lang::parse(&name).unwrap_or_else(|| fail(lang, "cli.unknown_lang", &[name]));
+            }
+            _ => filename = Some(arg),
+        }
+    }
+    if options.target != "python" && options.target != "c" {
+        fail(lang, "cli.unknown_target", &[options.target]);
+    }
+    let Some(filename) = filename else {
+        fail(lang, "cli.missing_argument", &[]);
+    };
+    match read_from_file(&filename) {
+        Ok(code_string) => run(code_string, filename, &options, lang),
+        Err(e) => fail(lang, "cli.read_failed", &[filename, e.to_string()]),
     }
 }
@@ -0,0 +1,514 @@
+use std::env;
+
+// 出力メッセージの言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Ja,
+    En,
+}
+
+impl Lang {
+    pub fn parse(name: &str) -> Option<Lang> {
+        match name {
+            "ja" => Some(Lang::Ja),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    // `LANG=ja_JP.UTF-8` のような環境変数から言語を決める
+    pub fn from_env() -> Lang {
+        match env::var("LANG") {
+            Ok(lang) if lang.starts_with("ja") => Lang::Ja,
+            _ => Lang::En,
+        }
+    }
+}
+
+// (キー, 日本語, 英語)。`{0}`, `{1}` … は引数に置き換えられる
+const CATALOG: &[(&str, &str, &str)] = &[
+    // 診断のメッセージ (エラーコードがキー)
+    (
+        "TF0001",
+        "{0} が期待されていましたが、{1} が見つかりました。",
+        "expected {0}, found {1}",
+    ),
+    (
+        "TF0002",
+        "式が期待されていましたが、{0} が見つかりました。",
+        "expected expression, found {0}",
+    ),
+    (
+        "TF0003",
+        "識別子が期待されていましたが、{0} が見つかりました。",
+        "expected identifier, found {0}",
+    ),
+    (
+        "TF0004",
+        "使用できない文字 `{0}` があります。",
+        "invalid character `{0}`",
+    ),
+    (
+        "TF0005",
+        "`{0}` は数値として読み込めません。",
+        "invalid number literal `{0}`",
+    ),
+    (
+        "TF0006",
+        "文字列が閉じられていません。",
+        "unterminated string literal",
+    ),
+    (
+        "TF0007",
+        "不明なエスケープシーケンス `{0}` です。",
+        "unknown escape sequence `{0}`",
+    ),
+    (
+        "TF0008",
+        "文字列中の `{` が閉じられていません。",
+        "unclosed `{` in string interpolation",
+    ),
+    (
+        "TF0009",
+        "コメントが閉じられていません。",
+        "unterminated block comment",
+    ),
+    (
+        "TF0010",
+        "この式には代入できません。",
+        "invalid left-hand side of assignment",
+    ),
+    (
+        "TF0011",
+        "初期値のない引数 `{0}` が初期値のある引数の後にあります。",
+        "parameter `{0}` without a default follows a parameter with a default",
+    ),
+    (
+        "TF0012",
+        "キーワード引数の後に位置引数があります。",
+        "positional argument follows keyword argument",
+    ),
+    (
+        "TF0101",
+        "{0}が定義されていません。",
+        "cannot find `{0}` in this scope",
+    ),
+    (
+        "TF0102",
+        "`{0}` はループの外では使えません。",
+        "`{0}` outside of a loop",
+    ),
+    (
+        "TF0103",
+        "ラベル `@{0}` は定義されていません。",
+        "use of undeclared label `@{0}`",
+    ),
+    (
+        "TF0104",
+        "`{0}` に渡す引数の数が正しくありません。",
+        "wrong number of arguments to `{0}`",
+    ),
+    (
+        "TF0105",
+        "`{0}` に `{1}` という引数はありません。",
+        "`{0}` has no parameter named `{1}`",
+    ),
+    (
+        "TF0106",
+        "引数 `{0}` が2回渡されています。",
+        "argument `{0}` given more than once",
+    ),
+    (
+        "TF0107",
+        "`{0}` に `{1}` というメンバーはありません。",
+        "no field or method named `{1}` on `{0}`",
+    ),
+    (
+        "TF0108",
+        "基底クラス `{0}` が定義されていません。",
+        "cannot find base class `{0}`",
+    ),
+    (
+        "TF0109",
+        "メソッド `{0}` の型が基底クラス `{1}` のものと違います。",
+        "method `{0}` does not match its signature in base class `{1}`",
+    ),
+    (
+        "TF0110",
+        "`super` は基底クラスのあるクラスの中でしか使えません。",
+        "`super` used outside of a class with a base class",
+    ),
+    (
+        "TF0111",
+        "列挙型 `{0}` に `{1}` という値はありません。",
+        "no variant named `{1}` in enum `{0}`",
+    ),
+    (
+        "TF0112",
+        "`match` ですべての場合が扱われていません。",
+        "non-exhaustive `match`",
+    ),
+    (
+        "TF0113",
+        "定数 `{0}` の値をコンパイル時に計算できません。",
+        "the value of constant `{0}` cannot be computed at compile time",
+    ),
+    (
+        "TF0114",
+        "定数 `{0}` には代入できません。",
+        "cannot assign to constant `{0}`",
+    ),
+    (
+        "TF0115",
+        "import が循環しています: {0}",
+        "import cycle: {0}",
+    ),
+    (
+        "TF0116",
+        "モジュール `{0}` に `{1}` はありません。",
+        "module `{0}` has no exported `{1}`",
+    ),
+    (
+        "TF0117",
+        "`{0}` が外側の同じ名前を隠しています。",
+        "`{0}` shadows an outer declaration",
+    ),
+    (
+        "TF0118",
+        "`{0}` が同じスコープで二度定義されています。",
+        "`{0}` is defined more than once in the same scope",
+    ),
+    (
+        "TF0119",
+        "`{0}` 型が必要ですが、`{1}` 型の値です。",
+        "mismatched types: expected `{0}`, found `{1}`",
+    ),
+    (
+        "TF0120",
+        "{0} は `{1}` と `{2}` には使えません。",
+        "cannot apply {0} to `{1}` and `{2}`",
+    ),
+    (
+        "TF0121",
+        "型 `{0}` が定義されていません。",
+        "cannot find type `{0}` in this scope",
+    ),
+    (
+        "TF0201",
+        "{0} は {1} への変換に対応していません。",
+        "{0} is not supported when generating {1}",
+    ),
+    (
+        "TF0202",
+        "map `{0}` の要素は {1} 個ですが、C の map に入るのは {2} 個までです。",
+        "map `{0}` has {1} entries, but a map holds at most {2} entries in C",
+    ),
+    (
+        "TF0301",
+        "{0} に書き込めませんでした: {1}",
+        "failed to write {0}: {1}",
+    ),
+    (
+        "TF0302",
+        "モジュール {0} を読み込めませんでした: {1}",
+        "failed to read module {0}: {1}",
+    ),
+    // ラベルと注記
+    (
+        "label.unexpected",
+        "{0} は予期されていません",
+        "unexpected {0}",
+    ),
+    (
+        "label.unclosed_block",
+        "このブロックは閉じられていません",
+        "this block is never closed",
+    ),
+    (
+        "label.invalid_character",
+        "この文字は使えません",
+        "not allowed here",
+    ),
+    (
+        "label.invalid_number",
+        "不正な数値リテラル",
+        "invalid number",
+    ),
+    ("label.undefined", "未定義の名前", "not defined"),
+    (
+        "label.invalid_assign_target",
+        "代入できるのは変数、`xs[i]`、`a.b` だけです",
+        "cannot assign to this expression",
+    ),
+    (
+        "label.missing_default",
+        "この引数にも初期値が必要です",
+        "this parameter needs a default value",
+    ),
+    (
+        "label.expected_args",
+        "引数は {0} 個必要ですが、{1} 個渡されています",
+        "expected {0} arguments, found {1}",
+    ),
+    (
+        "label.expected_args_range",
+        "引数は {0} 個から {1} 個必要ですが、{2} 個渡されています",
+        "expected {0} to {1} arguments, found {2}",
+    ),
+    (
+        "label.defined_here",
+        "ここで定義されています",
+        "defined here",
+    ),
+    (
+        "label.positional_argument",
+        "位置引数はキーワード引数より前に書いてください",
+        "positional arguments must come before keyword arguments",
+    ),
+    (
+        "label.missing_argument",
+        "引数 `{0}` が渡されていません",
+        "missing argument `{0}`",
+    ),
+    ("label.unknown_keyword", "不明な引数名", "unknown parameter"),
+    ("label.unknown_member", "未定義のメンバー", "unknown member"),
+    (
+        "label.unknown_base_class",
+        "基底クラスはこのクラスより前に定義してください",
+        "base classes must be defined before the classes that extend them",
+    ),
+    (
+        "label.override_mismatch",
+        "引数と戻り値の型を基底クラスのメソッドと同じにしてください",
+        "parameters and return type must match the overridden method",
+    ),
+    ("label.unknown_variant", "未定義の値", "unknown variant"),
+    (
+        "label.missing_variants",
+        "{0} が扱われていません",
+        "{0} not covered",
+    ),
+    (
+        "label.missing_else",
+        "`else` の腕を追加してください",
+        "add an `else` arm",
+    ),
+    (
+        "label.not_constant",
+        "定数式ではありません",
+        "not a constant expression",
+    ),
+    (
+        "label.missing_value",
+        "定数には値が必要です",
+        "constants need a value",
+    ),
+    (
+        "label.division_by_zero",
+        "0 で割っています",
+        "division by zero",
+    ),
+    (
+        "label.assign_to_constant",
+        "定数は書き換えられません",
+        "constants cannot be reassigned",
+    ),
+    (
+        "label.import_cycle",
+        "ここで `{0}` に戻っています",
+        "`{0}` is imported again here",
+    ),
+    (
+        "label.shadows",
+        "ここから外側の宣言が使えなくなります",
+        "the outer declaration is hidden from here",
+    ),
+    (
+        "label.duplicate_definition",
+        "二度目の定義",
+        "redefined here",
+    ),
+    (
+        "label.found_type",
+        "`{0}` 型の値",
+        "this is `{0}`",
+    ),
+    (
+        "label.expected_type",
+        "ここで `{0}` 型と宣言されています",
+        "expected `{0}` because of this",
+    ),
+    (
+        "label.return_type",
+        "この関数は `{0}` 型を返します",
+        "the function returns `{0}`",
+    ),
+    (
+        "label.invalid_operands",
+        "この型どうしでは計算できません",
+        "invalid operand types",
+    ),
+    (
+        "label.previous_definition",
+        "前の宣言はここです",
+        "previous declaration here",
+    ),
+    (
+        "label.unknown_import",
+        "関数、クラス、列挙型、定数だけを import できます",
+        "only functions, classes, enums and constants can be imported",
+    ),
+    (
+        "label.import",
+        "ここで import しています",
+        "imported here",
+    ),
+    (
+        "label.super_without_base",
+        "このクラスには基底クラスがありません",
+        "no base class here",
+    ),
+    (
+        "label.duplicate_argument",
+        "この引数はすでに渡されています",
+        "already given",
+    ),
+    (
+        "label.outside_loop",
+        "`while` か `for` の中でだけ使えます",
+        "cannot `{0}` outside of a loop",
+    ),
+    (
+        "label.undeclared_label",
+        "このラベルの付いたループの中ではありません",
+        "no enclosing loop has this label",
+    ),
+    (
+        "label.map_capacity",
+        "`TF_MAP_CAPACITY` を超えています",
+        "exceeds `TF_MAP_CAPACITY`",
+    ),
+    (
+        "label.unsupported",
+        "この出力先では使えません",
+        "not supported by this target",
+    ),
+    (
+        "label.unknown_type",
+        "型がわからない値",
+        "the type of this value is unknown",
+    ),
+    (
+        "label.string_start",
+        "この文字列は閉じられていません",
+        "string starts here",
+    ),
+    (
+        "label.comment_start",
+        "このコメントは `*/` で閉じられていません",
+        "comment starts here",
+    ),
+    (
+        "label.interpolation_start",
+        "埋め込み式はここから始まっています",
+        "interpolation starts here",
+    ),
+    (
+        "label.unknown_escape",
+        "使えるのは \\n \\t \\r \\0 \\\\ \\\" \\' \\{ \\} です",
+        "valid escapes are \\n \\t \\r \\0 \\\\ \\\" \\' \\{ \\}",
+    ),
+    (
+        "note.raw_module",
+        "直接記入モジュールに含まれていれば問題ありません。",
+        "this is fine if it is provided by a `language` block",
+    ),
+    (
+        "note.top_level",
+        "トップレベルに書けるのは関数・クラス・列挙型・定数・グローバル変数・import・language ブロックです",
+        "only functions, classes, enums, constants, globals, imports and `language` blocks may appear at the top level",
+    ),
+    (
+        "note.class_body",
+        "クラスの中に書けるのはフィールドとメソッドです",
+        "only fields and methods may appear in a class body",
+    ),
+    // 診断の見出し
+    ("severity.error", "エラー", "error"),
+    ("severity.warning", "警告", "warning"),
+    ("severity.note", "注記", "note"),
+    // コマンドライン
+    (
+        "cli.missing_argument",
+        "引数が足りません: tuningfork <ファイル> [--target python|c] [--lang ja|en] [--path <ディレクトリ>] [--bundle]",
+        "missing input file: tuningfork <file> [--target python|c] [--lang ja|en] [--path <dir>] [--bundle]",
+    ),
+    (
+        "cli.unknown_lang",
+        "不明な言語 `{0}` です (ja か en を指定してください)",
+        "unknown language `{0}` (expected ja or en)",
+    ),
+    (
+        "cli.unknown_target",
+        "不明なターゲット `{0}` です (python か c を指定してください)",
+        "unknown target `{0}` (expected python or c)",
+    ),
+    (
+        "cli.read_failed",
+        "{0} を読み込めませんでした: {1}",
+        "failed to read {0}: {1}",
+    ),
+    (
+        "cli.file_written",
+        "{0} を出力しました。",
+        "File '{0}' created and written successfully.",
+    ),
+];
+
+// キーに対応するメッセージを `lang` で組み立てる
+pub fn message(lang: Lang, key: &str, args: &[String]) -> String {
+    let template = CATALOG
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, ja, en)| match lang {
+            Lang::Ja => *ja,
+            Lang::En => *en,
+        })
+        .unwrap_or(key);
+
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text += &rest[..open];
+        rest = &rest[open..];
+        let close = rest.find('}').unwrap_or(0);
+        match rest[1..close.max(1)].parse::<usize>() {
+            Ok(index) if index < args.len() => {
+                text += &args[index];
+                rest = &rest[close + 1..];
+            }
+            _ => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text + rest
+}
+
+// 後から言語を決めて組み立てるメッセージ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub key: &'static str,
+    pub args: Vec<String>,
+}
+
+impl Message {
+    pub fn new(key: &'static str, args: Vec<String>) -> Self {
+        Self { key, args }
+    }
+
+    pub fn render(&self, lang: Lang) -> String {
+        message(lang, self.key, &self.args)
+    }
+}
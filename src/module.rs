@@ -0,0 +1,374 @@
+use crate::diagnostic::{self, Diagnostic};
+use crate::parse::{self, Node, NodeKind};
+use crate::token;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 生成するファイルの役割
+#[derive(Clone, Copy, PartialEq)]
+pub enum Role {
+    // コマンドラインで指定したファイル
+    Program,
+    // import されるファイル (main を呼ばず、C ではヘッダーも出力する)
+    Module,
+    // 1つのファイルにまとめるときの、入口以外のファイル (何も書き出さない)
+    Bundled,
+}
+
+// .tf ファイルを読み込んだ import 文
+pub struct Import {
+    // `a.b` の形の名前
+    pub path: String,
+    // このファイルで使える名前 (モジュールでの名前と、このファイルでの名前)。
+    // `import a as b;` ではモジュールの名前で使うので空になる
+    pub names: Vec<(String, String)>,
+    // load が返す列の中の位置
+    pub module: usize,
+}
+
+// 読み込んだ .tf ファイル
+pub struct Module {
+    pub filename: String,
+    pub source: String,
+    pub ast: Node,
+    pub diagnostics: Vec<Diagnostic>,
+    pub imports: Vec<Import>,
+}
+
+impl Module {
+    fn definitions(&self) -> &[Node] {
+        match &self.ast.kind {
+            Some(NodeKind::Root { function_define_s }) => function_define_s,
+            _ => &[],
+        }
+    }
+
+    // 他のファイルから使える名前 (関数、クラス、列挙型、定数)
+    pub fn exported_names(&self) -> Vec<String> {
+        self.definitions()
+            .iter()
+            .filter_map(|node| match &node.kind {
+                Some(NodeKind::Function {
+                    function_name: token::Type::Identifier(name),
+                    ..
+                }) => Some(name.clone()),
+                Some(NodeKind::Class { class_name, .. }) => Some(class_name.clone()),
+                Some(NodeKind::Enum { name, .. }) => Some(name.clone()),
+                Some(NodeKind::Global {
+                    declaration,
+                    is_const: true,
+                }) => match &declaration.kind {
+                    Some(NodeKind::Let { v_name, .. }) => Some(v_name.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 別名で import した名前 (モジュールでの名前と、このファイルでの名前)
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        self.imports
+            .iter()
+            .flat_map(|import| &import.names)
+            .filter(|(name, local)| name != local)
+            .cloned()
+            .collect()
+    }
+}
+
+// modules[index] から直接・間接に import されているモジュールの位置 (load が返す順)
+pub fn reachable(modules: &[Module], index: usize) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut pending: Vec<usize> = modules[index]
+        .imports
+        .iter()
+        .map(|import| import.module)
+        .collect();
+    while let Some(next) = pending.pop() {
+        if !found.contains(&next) {
+            found.push(next);
+            pending.extend(modules[next].imports.iter().map(|import| import.module));
+        }
+    }
+    found.sort_unstable();
+    found
+}
+
+// import で使う名前 (ファイル名から拡張子を除いたもの)
+fn module_name(filename: &str) -> String {
+    Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// 同じファイルを別の書き方で import しても1回だけ読み込むように、パスをそろえる
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+struct Loader {
+    search_path: Vec<PathBuf>,
+    modules: Vec<Module>,
+    loaded: HashMap<PathBuf, usize>,
+    // 読み込み中のファイルと名前 (import の循環を見つけるのに使う)
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl Loader {
+    // `import a.b;` のファイル (`a/b.tf`) を、import しているファイルのディレクトリ、検索パスの順に探す
+    fn find(&self, importer: &str, path: &[String]) -> Option<PathBuf> {
+        let file = format!("{}.tf", path.join("/"));
+        let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        std::iter::once(dir)
+            .chain(self.search_path.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+    }
+
+    fn visit(&mut self, filename: String, source: String) -> usize {
+        let path = canonical(Path::new(&filename));
+        let mut lexer = token::Lexer::new(source.clone());
+        let tokens = lexer.lex();
+        let mut diagnostics = std::mem::take(&mut lexer.diagnostics);
+        let ast = match parse::Parser::new(&tokens).root() {
+            Ok(ast) => ast,
+            Err(errors) => {
+                diagnostics.extend(errors);
+                Node::default()
+            }
+        };
+
+        self.loading.push((path.clone(), module_name(&filename)));
+        let mut imports = Vec::new();
+        let definitions = match &ast.kind {
+            Some(NodeKind::Root { function_define_s }) => function_define_s.as_slice(),
+            _ => &[],
+        };
+        for node in definitions {
+            let Some(NodeKind::Import { path, names, alias }) = &node.kind else {
+                continue;
+            };
+            // .tf が見つからなければ Python のモジュールとしてそのまま import する
+            let Some(import_file) = self.find(&filename, path) else {
+                continue;
+            };
+            let import = path.join(".");
+            let import_path = canonical(&import_file);
+            if let Some(position) = self.loading.iter().position(|(p, _)| *p == import_path) {
+                let cycle = &self.loading[position..];
+                let chain: Vec<&str> = cycle
+                    .iter()
+                    .chain(&cycle[..1])
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                diagnostics.push(
+                    Diagnostic::error(diagnostic::IMPORT_CYCLE, vec![chain.join(" -> ")])
+                        .with_label(node.span, "label.import_cycle", vec![import]),
+                );
+                continue;
+            }
+            let index = match self.loaded.get(&import_path) {
+                Some(index) => *index,
+                None => {
+                    let import_filename = import_file.to_string_lossy().into_owned();
+                    match fs::read_to_string(&import_file) {
+                        Ok(source) => self.visit(import_filename, source),
+                        Err(e) => {
+                            diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::MODULE_READ_FAILED,
+                                    vec![import_filename, e.to_string()],
+                                )
+                                .with_label(
+                                    node.span,
+                                    "label.import",
+                                    vec![],
+                                ),
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+            let exports = self.modules[index].exported_names();
+            let names = match (names, alias) {
+                (Some(names), _) => names
+                    .iter()
+                    .filter_map(|(name, local, span)| {
+                        if exports.contains(name) {
+                            let local = local.clone().unwrap_or_else(|| name.clone());
+                            return Some((name.clone(), local));
+                        }
+                        diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::UNKNOWN_IMPORT,
+                                vec![import.clone(), name.clone()],
+                            )
+                            .with_label(
+                                *span,
+                                "label.unknown_import",
+                                vec![],
+                            ),
+                        );
+                        None
+                    })
+                    .collect(),
+                (None, Some(_)) => Vec::new(),
+                (None, None) => exports
+                    .into_iter()
+                    .map(|name| (name.clone(), name))
+                    .collect(),
+            };
+            imports.push(Import {
+                path: import,
+                names,
+                module: index,
+            });
+        }
+        self.loading.pop();
+
+        let index = self.modules.len();
+        self.modules.push(Module {
+            filename,
+            source,
+            ast,
+            diagnostics,
+            imports,
+        });
+        self.loaded.insert(path, index);
+        index
+    }
+}
+
+// 入口のファイルと、そこから import されるファイルを読み込む。
+// import されるものが先に並び、入口のファイルは最後になる
+pub fn load(filename: String, source: String, search_path: Vec<PathBuf>) -> Vec<Module> {
+    let mut loader = Loader {
+        search_path,
+        modules: Vec::new(),
+        loaded: HashMap::new(),
+        loading: Vec::new(),
+    };
+    loader.visit(filename, source);
+    loader.modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // テストごとの作業ディレクトリに files を書き出し、main.tf から読み込む
+    fn load_files(name: &str, files: &[(&str, &str)], search_path: &[&str]) -> Vec<Module> {
+        let dir =
+            std::env::temp_dir().join(format!("tuningfork-module-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, source) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        let main = dir.join("main.tf");
+        let source = fs::read_to_string(&main).unwrap();
+        let search_path = search_path.iter().map(|path| dir.join(path)).collect();
+        let modules = load(main.to_string_lossy().into_owned(), source, search_path);
+        let _ = fs::remove_dir_all(&dir);
+        modules
+    }
+
+    fn names(modules: &[Module]) -> Vec<String> {
+        modules
+            .iter()
+            .map(|module| module_name(&module.filename))
+            .collect()
+    }
+
+    fn codes(module: &Module) -> Vec<&'static str> {
+        module
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn imports_are_loaded_once_before_the_importer() {
+        let modules = load_files(
+            "order",
+            &[
+                (
+                    "main.tf",
+                    "import drive;\nimport robot.arm.{lift as up};\nimport pybricks.tools;\n",
+                ),
+                (
+                    "drive.tf",
+                    "import robot.arm;\nint: forward() <- {\n    return 1;\n}\n",
+                ),
+                (
+                    "lib/robot/arm.tf",
+                    "const int: HEIGHT <- 3;\nvoid: lift() <- {\n}\n",
+                ),
+            ],
+            &["lib"],
+        );
+        assert_eq!(names(&modules), vec!["arm", "drive", "main"]);
+        assert!(modules.iter().all(|module| module.diagnostics.is_empty()));
+        let main = &modules[2];
+        // .tf が見つからない pybricks.tools は Python のモジュールのまま
+        assert_eq!(main.imports.len(), 2);
+        assert_eq!(main.imports[0].path, "drive");
+        assert_eq!(
+            main.imports[0].names,
+            vec![("forward".to_string(), "forward".to_string())]
+        );
+        assert_eq!(main.imports[1].path, "robot.arm");
+        assert_eq!(main.imports[1].module, 0);
+        assert_eq!(
+            main.imports[1].names,
+            vec![("lift".to_string(), "up".to_string())]
+        );
+        assert_eq!(modules[0].exported_names(), vec!["HEIGHT", "lift"]);
+        assert_eq!(reachable(&modules, 2), vec![0, 1]);
+    }
+
+    #[test]
+    fn import_cycles_are_reported() {
+        let modules = load_files(
+            "cycle",
+            &[
+                ("main.tf", "import a;\n"),
+                ("a.tf", "import b;\n"),
+                ("b.tf", "import a;\n"),
+            ],
+            &[],
+        );
+        assert_eq!(names(&modules), vec!["b", "a", "main"]);
+        assert_eq!(codes(&modules[0]), vec![diagnostic::IMPORT_CYCLE]);
+        assert_eq!(
+            modules[0].diagnostics[0].args,
+            vec!["a -> b -> a".to_string()]
+        );
+        assert!(codes(&modules[1]).is_empty());
+    }
+
+    #[test]
+    fn unknown_names_in_an_import_are_reported() {
+        let modules = load_files(
+            "unknown",
+            &[
+                ("main.tf", "import drive.{forward, fly};\n"),
+                ("drive.tf", "void: forward() <- {\n}\n"),
+            ],
+            &[],
+        );
+        let main = &modules[1];
+        assert_eq!(codes(main), vec![diagnostic::UNKNOWN_IMPORT]);
+        assert_eq!(main.diagnostics[0].labels[0].span.column, 24);
+        assert_eq!(
+            main.imports[0].names,
+            vec![("forward".to_string(), "forward".to_string())]
+        );
+    }
+}
@@ -1,39 +1,101 @@
-use crate::token::Type;
-
-const RAW_DATA_TYPE_PYTHON: i32 = 0;
-const RAW_DATA_TYPE_C: i32 = 1;
-const RAW_DATA_TYPE_CPP: i32 = 2;
-const RAW_DATA_TYPE_RUST: i32 = 3;
-
-fn type_of<T>(_: &T) -> &'static str {
-    std::any::type_name::<T>()
+use crate::diagnostic::{self, Diagnostic};
+use crate::token::{Span, TemplatePart, Token, Type};
+use std::collections::HashSet;
+
+pub const RAW_DATA_TYPE_PYTHON: i32 = 0;
+pub const RAW_DATA_TYPE_C: i32 = 1;
+pub const RAW_DATA_TYPE_CPP: i32 = 2;
+pub const RAW_DATA_TYPE_RUST: i32 = 3;
+
+// コンストラクタの名前
+pub const CONSTRUCTOR_NAME: &str = "_init_";
+
+// 演算子の優先順位 (大きいほど強く結びつく)
+pub const PRECEDENCE_OR: u8 = 1;
+pub const PRECEDENCE_AND: u8 = 2;
+pub const PRECEDENCE_NOT: u8 = 3;
+pub const PRECEDENCE_COMPARE: u8 = 4;
+pub const PRECEDENCE_UNARY: u8 = 7;
+pub const PRECEDENCE_ATOM: u8 = u8::MAX;
+
+// 二項演算子の優先順位
+pub fn precedence(op: &Type) -> Option<u8> {
+    match op {
+        Type::Or => Some(PRECEDENCE_OR),
+        Type::And => Some(PRECEDENCE_AND),
+        Type::Less
+        | Type::Greater
+        | Type::LessEqual
+        | Type::GreaterEqual
+        | Type::EqualEqual
+        | Type::NotEqual => Some(PRECEDENCE_COMPARE),
+        Type::Plus | Type::Minus => Some(5),
+        Type::Asterisk | Type::Slash => Some(6),
+        _ => None,
+    }
 }
+
 #[derive(Clone, PartialEq)]
 pub enum NodeKind {
     Num(i32),
+    Float(f64),
+    // true / false
+    Bool(bool),
     Str(String),
+    // 文字列リテラル (エスケープ済みの中身)
+    StrLiteral(String),
+    // `"speed={s}"` のような埋め込み式付き文字列。StrLiteral と式が交互に並ぶ
+    FormatStr(Vec<Node>),
     Pass(String),
     BinaryOp {
         op: Type,
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
+    UnaryOp {
+        op: Type,
+        operand: Box<Node>,
+    },
+    #[allow(dead_code)]
     VarRef(String),
     Assign {
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
+    List(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+    Index {
+        target: Box<Node>,
+        index: Box<Node>,
+    },
     Compare {
         lhs: Box<Node>,
         op: Box<Type>,
         rhs: Box<Node>,
     },
-    CallMenber {
-        now_menber_name: String,
-        next: Box<Node>,
+    Logical {
+        lhs: Box<Node>,
+        op: Type,
+        rhs: Box<Node>,
+    },
+    Field {
+        target: Box<Node>,
+        name: String,
+    },
+    MethodCall {
+        target: Box<Node>,
+        name: String,
+        args: Vec<Node>,
+        kwargs: Vec<(String, Node)>,
     },
     Block(Vec<Node>),
-    Import(String),
+    // `import a.b;` `import a.b as c;` `import a.b.{X, Y as Z};`
+    Import {
+        path: Vec<String>,
+        // 選んで import する名前と別名
+        names: Option<Vec<(String, Option<String>, Span)>>,
+        alias: Option<String>,
+    },
     Let {
         v_name: String,
         v_type: String,
@@ -43,17 +105,29 @@ pub enum NodeKind {
     If {
         cond: Box<Node>,
         then: Box<Node>,
-        elif_then: Option<Vec<Box<Node>>>,
+        elif_then: Option<Vec<Node>>,
         else_then: Option<Box<Node>>,
     },
     Elif {
         cond: Box<Node>,
         then: Box<Node>,
-        else_then: Option<Box<Node>>,
     },
     While {
         cond: Box<Node>,
         body: Box<Node>,
+        label: Option<String>,
+    },
+    For {
+        var: String,
+        iter: Box<Node>,
+        body: Box<Node>,
+        label: Option<String>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    Range {
+        start: Box<Node>,
+        end: Box<Node>,
     },
     Function {
         params: Vec<Node>,
@@ -61,10 +135,17 @@ pub enum NodeKind {
         function_type: Type,
         function_name: Type,
         is_menber: bool,
+        doc: Option<String>,
     },
     Call {
         function_name: String,
         args: Vec<Node>,
+        kwargs: Vec<(String, Node)>,
+    },
+    Param {
+        name: String,
+        ty: Option<String>,
+        default: Option<Box<Node>>,
     },
     Return(Box<Node>),
     Expr {
@@ -76,653 +157,1809 @@ pub enum NodeKind {
     },
     Class {
         class_name: String,
+        // `class Fast : Base` の基底クラスと、その名前の位置
+        base: Option<(String, Span)>,
+        fields: Vec<Node>,
         menbers: Vec<Node>,
+        doc: Option<String>,
+    },
+    // トップレベルの変数 (`const int: SPEED <- 300;` / `int: count <- 0;`)。declaration は Let
+    Global {
+        declaration: Box<Node>,
+        is_const: bool,
+    },
+    // enum State <- { Idle, Driving }
+    Enum {
+        name: String,
+        variants: Vec<(String, Span)>,
+        doc: Option<String>,
+    },
+    // match value <- { State.Idle <- { ... } else <- { ... } }
+    Match {
+        value: Box<Node>,
+        arms: Vec<Node>,
+        default: Option<Box<Node>>,
+    },
+    MatchArm {
+        patterns: Vec<Node>,
+        body: Box<Node>,
     },
     Root {
         function_define_s: Vec<Node>,
     },
 }
-#[derive(Clone, PartialEq)]
+
+#[derive(Clone, PartialEq, Default)]
 pub struct Node {
     pub kind: Option<NodeKind>,
-    pub token: Type,
+    pub span: Span,
 }
 
 impl Node {
     pub fn new() -> Self {
         Self {
             kind: None,
-            token: Type::EOF,
+            span: Span::default(),
+        }
+    }
+
+    // 式としての優先順位 (括弧を付けるかどうかの判断に使う)
+    pub fn precedence(&self) -> u8 {
+        match &self.kind {
+            Some(NodeKind::BinaryOp { op, .. }) => precedence(op).unwrap_or(PRECEDENCE_ATOM),
+            Some(NodeKind::Compare { .. }) => PRECEDENCE_COMPARE,
+            Some(NodeKind::Logical { op, .. }) => precedence(op).unwrap_or(PRECEDENCE_ATOM),
+            Some(NodeKind::UnaryOp { op: Type::Not, .. }) => PRECEDENCE_NOT,
+            Some(NodeKind::UnaryOp { .. }) => PRECEDENCE_UNARY,
+            _ => PRECEDENCE_ATOM,
+        }
+    }
+
+    // 子のノード (検査で木をたどるときに使う)
+    pub fn children(&self) -> Vec<&Node> {
+        match &self.kind {
+            Some(
+                NodeKind::BinaryOp { lhs, rhs, .. }
+                | NodeKind::Compare { lhs, rhs, .. }
+                | NodeKind::Logical { lhs, rhs, .. }
+                | NodeKind::Assign { lhs, rhs },
+            ) => vec![lhs, rhs],
+            Some(NodeKind::UnaryOp { operand, .. }) => vec![operand],
+            Some(
+                NodeKind::FormatStr(nodes)
+                | NodeKind::List(nodes)
+                | NodeKind::Block(nodes)
+                | NodeKind::Root {
+                    function_define_s: nodes,
+                },
+            ) => nodes.iter().collect(),
+            Some(NodeKind::Call { args, kwargs, .. }) => args
+                .iter()
+                .chain(kwargs.iter().map(|(_, value)| value))
+                .collect(),
+            Some(NodeKind::Class {
+                fields, menbers, ..
+            }) => fields.iter().chain(menbers).collect(),
+            Some(NodeKind::Map(entries)) => entries
+                .iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+            Some(NodeKind::Index { target, index }) => vec![target, index],
+            Some(NodeKind::Field { target, .. }) => vec![target],
+            Some(NodeKind::MethodCall {
+                target,
+                args,
+                kwargs,
+                ..
+            }) => std::iter::once(&**target)
+                .chain(args)
+                .chain(kwargs.iter().map(|(_, value)| value))
+                .collect(),
+            Some(NodeKind::Let { v_formula, .. }) => vec![v_formula],
+            Some(NodeKind::Global { declaration, .. }) => vec![declaration],
+            Some(NodeKind::If {
+                cond,
+                then,
+                elif_then,
+                else_then,
+            }) => {
+                let mut children: Vec<&Node> = vec![cond, then];
+                children.extend(elif_then.iter().flatten());
+                children.extend(else_then.as_deref());
+                children
+            }
+            Some(NodeKind::Elif { cond, then }) => vec![cond, then],
+            Some(NodeKind::Match {
+                value,
+                arms,
+                default,
+            }) => {
+                let mut children: Vec<&Node> = vec![value];
+                children.extend(arms);
+                children.extend(default.as_deref());
+                children
+            }
+            Some(NodeKind::MatchArm { patterns, body }) => {
+                let mut children: Vec<&Node> = patterns.iter().collect();
+                children.push(body);
+                children
+            }
+            Some(NodeKind::While { cond, body, .. }) => vec![cond, body],
+            Some(NodeKind::For { iter, body, .. }) => vec![iter, body],
+            Some(NodeKind::Range { start, end }) => vec![start, end],
+            Some(NodeKind::Function { params, body, .. }) => {
+                let mut children: Vec<&Node> = params.iter().collect();
+                children.push(body);
+                children
+            }
+            Some(NodeKind::Param { default, .. }) => default.iter().map(|node| &**node).collect(),
+            Some(NodeKind::Return(node) | NodeKind::Expr { reserv: node }) => vec![node],
+            _ => Vec::new(),
+        }
+    }
+
+    // この中で宣言された名前 (引数、変数、for の変数)
+    pub fn declared_names(&self) -> HashSet<&String> {
+        let mut names = HashSet::new();
+        self.collect_declared_names(&mut names);
+        names
+    }
+
+    fn collect_declared_names<'a>(&'a self, names: &mut HashSet<&'a String>) {
+        if let Some(
+            NodeKind::Let { v_name: name, .. }
+            | NodeKind::Param { name, .. }
+            | NodeKind::For { var: name, .. },
+        ) = &self.kind
+        {
+            names.insert(name);
+        }
+        for child in self.children() {
+            child.collect_declared_names(names);
+        }
+    }
+
+    // `label` のループへ抜ける (break, continue) があるか
+    pub fn jumps_to(&self, label: &str) -> (bool, bool) {
+        let mut found = (false, false);
+        self.collect_nested_jumps(label, true, &mut found);
+        found
+    }
+
+    // 入れ子のループの中から `label` のループへ抜ける (break, continue) があるか
+    pub fn nested_jumps(&self, label: &str) -> (bool, bool) {
+        let mut found = (false, false);
+        self.collect_nested_jumps(label, false, &mut found);
+        found
+    }
+
+    fn collect_nested_jumps(&self, label: &str, in_loop: bool, found: &mut (bool, bool)) {
+        match &self.kind {
+            Some(NodeKind::Break(Some(target))) if in_loop && target == label => found.0 = true,
+            Some(NodeKind::Continue(Some(target))) if in_loop && target == label => found.1 = true,
+            Some(NodeKind::Expr { reserv }) => reserv.collect_nested_jumps(label, in_loop, found),
+            Some(NodeKind::Block(nodes)) => {
+                for node in nodes {
+                    node.collect_nested_jumps(label, in_loop, found);
+                }
+            }
+            Some(NodeKind::If {
+                then,
+                elif_then,
+                else_then,
+                ..
+            }) => {
+                then.collect_nested_jumps(label, in_loop, found);
+                for elif in elif_then.iter().flatten() {
+                    elif.collect_nested_jumps(label, in_loop, found);
+                }
+                if let Some(else_then) = else_then {
+                    else_then.collect_nested_jumps(label, in_loop, found);
+                }
+            }
+            Some(NodeKind::Elif { then, .. } | NodeKind::MatchArm { body: then, .. }) => {
+                then.collect_nested_jumps(label, in_loop, found)
+            }
+            Some(NodeKind::Match { arms, default, .. }) => {
+                for arm in arms.iter().chain(default.as_deref()) {
+                    arm.collect_nested_jumps(label, in_loop, found);
+                }
+            }
+            Some(NodeKind::While { body, .. } | NodeKind::For { body, .. }) => {
+                body.collect_nested_jumps(label, true, found)
+            }
+            _ => {}
         }
     }
 }
 
+// 初期値を書かなかった数値・文字列・bool の値 (0, 0.0, "", false)。それ以外の型は None
+pub fn default_value(v_type: &str, span: Span) -> Option<Node> {
+    let kind = match v_type {
+        "int" => NodeKind::Num(0),
+        "float" => NodeKind::Float(0.0),
+        "string" => NodeKind::StrLiteral(String::new()),
+        "bool" => NodeKind::Bool(false),
+        _ => return None,
+    };
+    Some(Node {
+        kind: Some(kind),
+        span,
+    })
+}
+
+// `self.name <- value;` の文
+pub fn field_assignment(name: &str, value: Node) -> Node {
+    let span = value.span;
+    let node = |kind| Node {
+        kind: Some(kind),
+        span,
+    };
+    node(NodeKind::Expr {
+        reserv: Box::new(node(NodeKind::Assign {
+            lhs: Box::new(node(NodeKind::Field {
+                target: Box::new(node(NodeKind::Str("self".to_string()))),
+                name: name.to_string(),
+            })),
+            rhs: Box::new(value),
+        })),
+    })
+}
+
+// クラスのメソッドからコンストラクタの引数を探す
+pub fn constructor_params(menbers: &[Node]) -> Option<Vec<Node>> {
+    menbers.iter().find_map(|menber| match &menber.kind {
+        Some(NodeKind::Function {
+            params,
+            function_name: Type::Identifier(name),
+            ..
+        }) if name == CONSTRUCTOR_NAME => Some(params.clone()),
+        _ => None,
+    })
+}
+
+// `super.name(args);` の文
+pub fn super_call(name: &str, args: Vec<Node>, span: Span) -> Node {
+    let node = |kind| Node {
+        kind: Some(kind),
+        span,
+    };
+    node(NodeKind::Expr {
+        reserv: Box::new(node(NodeKind::MethodCall {
+            target: Box::new(node(NodeKind::Str("super".to_string()))),
+            name: name.to_string(),
+            args,
+            kwargs: Vec::new(),
+        })),
+    })
+}
+
+// フィールドの初期化をコンストラクタの先頭に入れる。コンストラクタがなければ作る
+// 基底クラスがあるときは、作るコンストラクタが基底クラスのコンストラクタ (引数 inherited) を呼ぶ
+pub fn insert_field_initializers(
+    mut menbers: Vec<Node>,
+    mut initializers: Vec<Node>,
+    inherited: Option<Vec<Node>>,
+) -> Vec<Node> {
+    let constructor = menbers.iter_mut().find(|menber| {
+        matches!(&menber.kind, Some(NodeKind::Function {
+            function_name: Type::Identifier(name),
+            ..
+        }) if name == CONSTRUCTOR_NAME)
+    });
+    match constructor {
+        Some(Node {
+            kind: Some(NodeKind::Function { body, .. }),
+            ..
+        }) => {
+            if let Some(NodeKind::Block(statements)) = &mut body.kind {
+                statements.splice(0..0, initializers);
+            }
+        }
+        _ => {
+            let params = inherited.clone().unwrap_or_default();
+            if inherited.is_some() {
+                let args = params
+                    .iter()
+                    .filter_map(|param| match &param.kind {
+                        Some(NodeKind::Param { name, .. }) => Some(Node {
+                            kind: Some(NodeKind::Str(name.clone())),
+                            span: param.span,
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+                initializers.insert(0, super_call(CONSTRUCTOR_NAME, args, Span::default()));
+            }
+            menbers.insert(
+                0,
+                Node {
+                    kind: Some(NodeKind::Function {
+                        params,
+                        body: Box::new(Node {
+                            kind: Some(NodeKind::Block(initializers)),
+                            span: Span::default(),
+                        }),
+                        function_type: Type::Identifier("void".to_string()),
+                        function_name: Type::Identifier(CONSTRUCTOR_NAME.to_string()),
+                        is_menber: true,
+                        doc: None,
+                    }),
+                    span: Span::default(),
+                },
+            );
+        }
+    }
+    menbers
+}
+
+type PResult<T> = Result<T, Diagnostic>;
+
+// 呼び出しの位置引数とキーワード引数
+type Arguments = (Vec<Node>, Vec<(String, Node)>);
+
 pub struct Parser<'a> {
-    pub node: Node,
-    pub now_token: std::slice::Iter<'a, Type>,
-    pub tokens: &'a [Type],
-    pub function_argments: Vec<String>,
+    pub now_token: std::slice::Iter<'a, Token>,
+    pub tokens: &'a [Token],
     pub now_function_is_menber: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    prev_span: Span,
+    // 今いるループのラベル (内側が後ろ)
+    loop_labels: Vec<Option<String>>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a [Type]) -> Self {
+    pub fn new(tokens: &'a [Token]) -> Self {
         Self {
-            node: Node::new(),
             now_token: tokens.iter(),
             tokens,
-            function_argments: Vec::new(),
             now_function_is_menber: false,
+            diagnostics: Vec::new(),
+            prev_span: Span::default(),
+            loop_labels: Vec::new(),
         }
     }
 
-    fn skip(&mut self, expect_token: Type) -> bool {
-        let mut token = self.now_token.clone();
+    // 次のトークンを消費する (EOF より先には進まない)
+    fn next_token(&mut self) -> Token {
+        let token = match self.now_token.as_slice() {
+            [last] => last.clone(),
+            _ => self.now_token.next().unwrap().clone(),
+        };
+        self.prev_span = token.span;
+        token
+    }
 
-        if token.next().unwrap() == &expect_token {
-            self.now_token.next();
+    // 次のトークンの直前にある `///` コメント
+    fn now_doc_comment(&self) -> Option<String> {
+        self.now_token
+            .as_slice()
+            .first()
+            .and_then(|token| token.doc_comment.clone())
+    }
+
+    // 次のトークンの位置
+    fn now_span(&self) -> Span {
+        match self.now_token.as_slice().first() {
+            Some(token) => token.span,
+            None => self.prev_span,
+        }
+    }
+
+    // start から直前に消費したトークンまでの Span
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span)
+    }
+
+    fn skip(&mut self, expect_token: Type) -> bool {
+        if self.sheek_token(1) == expect_token {
+            self.next_token();
             true
         } else {
             false
         }
     }
 
-    fn expect(&mut self, expect_token: Type) -> bool {
-        //        self.skip(Type::Enter);
-        let mut token = self.now_token.clone();
-        let t2 = token.next().unwrap().clone();
-        if t2 == Type::Enter {
-            self.now_token.next();
-            self.now_token.next();
-        }
-        match self.now_token.next().unwrap().clone() == expect_token {
-            true => true,
-            false => false,
-        }
+    // 期待していないトークンが来たときのエラー (args の最後に見つかったトークンが入る)
+    fn unexpected(&self, code: &'static str, mut args: Vec<String>) -> Diagnostic {
+        let found = self.sheek_token(1).to_string();
+        args.push(found.clone());
+        Diagnostic::error(code, args).with_label(self.now_span(), "label.unexpected", vec![found])
     }
 
-    fn expect_err(&mut self, expect_token: Type) -> bool {
-        if self.expect(expect_token.clone()) {
-            true
+    fn expect_err(&mut self, expect_token: Type) -> PResult<Token> {
+        if self.sheek_token(1) == expect_token {
+            Ok(self.next_token())
         } else {
-            panic!("Syntax error: {:?} が期待されていました。", expect_token,)
+            Err(self.unexpected(diagnostic::UNEXPECTED_TOKEN, vec![expect_token.to_string()]))
         }
     }
 
-    fn get_identifier_contents(&mut self, data: Type) -> Result<String, &'static str> {
-        if let Type::Identifier(word) = data {
-            return Ok(word);
+    fn expect_identifier(&mut self) -> PResult<String> {
+        if let Type::Identifier(word) = self.sheek_token(1) {
+            self.next_token();
+            Ok(word)
         } else {
-            return Err("err");
+            Err(self.unexpected(diagnostic::EXPECTED_IDENTIFIER, vec![]))
         }
     }
 
-    pub fn sheek_token(&mut self, index: i32) -> Type {
+    pub fn sheek_token(&self, index: i32) -> Type {
         let mut tmp_token = self.now_token.clone();
 
         for _ in 0..index - 1 {
             tmp_token.next();
         }
-        tmp_token.next().unwrap().clone()
+        match tmp_token.next() {
+            Some(token) => token.token_type.clone(),
+            None => Type::EOF,
+        }
     }
 
-    pub fn sheek_watch_token(&mut self, index: i32) -> Type {
-        let tmp_token = self.sheek_token(index);
-
-        println!("{:?}", tmp_token);
-        tmp_token
+    // 文の途中でエラーが起きたとき、次の `;` か、ブロックの `}` まで読み飛ばす
+    fn synchronize_statement(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.sheek_token(1) {
+                Type::EOF => return,
+                Type::SemiColon if depth == 0 => {
+                    self.next_token();
+                    return;
+                }
+                Type::LBraces => depth += 1,
+                Type::RBraces if depth == 0 => return,
+                Type::RBraces => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.next_token();
+                        self.skip(Type::SemiColon);
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.next_token();
+        }
     }
 
-    pub fn safty_sheek_watch_token(
-        &mut self,
-        now_token: std::slice::Iter<Type>,
-        index: i32,
-    ) -> Type {
-        let mut tmp_token = now_token.clone();
-
-        for _ in 0..index - 1 {
-            tmp_token.next();
+    // 宣言の途中でエラーが起きたとき、宣言の終わりか次のトップレベル宣言まで読み飛ばす
+    fn synchronize_declaration(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.sheek_token(1) {
+                Type::EOF => return,
+                Type::RBraces if depth == 0 => return,
+                Type::LBraces => depth += 1,
+                Type::RBraces => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.next_token();
+                        return;
+                    }
+                }
+                Type::Identifier(word)
+                    if depth == 0 && matches!(word.as_str(), "import" | "class" | "language") =>
+                {
+                    return
+                }
+                _ => {}
+            }
+            self.next_token();
         }
-        println!("{:?}", tmp_token.clone().next());
-        tmp_token.next().unwrap().clone()
     }
 
-    pub fn safety_sheek_token(&mut self, now_token: std::slice::Iter<Type>, index: i32) -> Type {
-        let mut tmp_token = now_token.clone();
-
-        for _ in 0..index - 1 {
-            tmp_token.next();
-        }
-        tmp_token.next().unwrap().clone()
+    fn number(&mut self) -> PResult<Node> {
+        let kind = match self.sheek_token(1) {
+            Type::DoubleQuotation(word) => NodeKind::StrLiteral(word),
+            Type::Template(parts) => NodeKind::FormatStr(self.template(parts)?),
+            Type::Number(number) => NodeKind::Num(number as i32),
+            Type::Float(number) => NodeKind::Float(number),
+            Type::Identifier(word) if word == "true" || word == "false" => {
+                NodeKind::Bool(word == "true")
+            }
+            Type::Identifier(string) => NodeKind::Str(string),
+            _ => return Err(self.unexpected(diagnostic::EXPECTED_EXPRESSION, vec![])),
+        };
+        let token = self.next_token();
+        Ok(Node {
+            kind: Some(kind),
+            span: token.span,
+        })
     }
 
-    fn word(&mut self) -> Node {
-        let token = self.now_token.clone();
-        let mut token2 = self.now_token.clone();
+    // 埋め込み式付き文字列の各部分を読む
+    fn template(&mut self, parts: Vec<TemplatePart>) -> PResult<Vec<Node>> {
+        let span = self.now_span();
+        let mut nodes = Vec::new();
 
-        if let Type::Identifier(word) = token.clone().next().unwrap().clone() {
-            Node {
-                kind: Some(NodeKind::Str(word)),
-                token: token.clone().next().unwrap().clone(),
+        for part in parts {
+            match part {
+                TemplatePart::Text(text) => nodes.push(Node {
+                    kind: Some(NodeKind::StrLiteral(text)),
+                    span,
+                }),
+                TemplatePart::Code(tokens) => {
+                    let mut parser = Parser::new(&tokens);
+                    let node = parser.expression()?;
+                    parser.expect_err(Type::EOF)?;
+                    nodes.push(node);
+                }
             }
-        } else {
-            panic!(
-                "Typeが異なります!!!\n    予期されたタイプ: {:?}\n    確認されたタイプ: {:?}",
-                token.clone().next().unwrap().clone(),
-                token2.next().unwrap().clone()
-            );
         }
+        Ok(nodes)
     }
-    fn number(&mut self) -> Node {
-        let mut token = self.now_token.clone();
 
-        if let Type::DoubleQuotation(word) = self.now_token.clone().next().unwrap().clone() {
-            let s = format!("\"{}\"", word);
-            return Node {
-                kind: Some(NodeKind::Str(s)),
-                token: self.now_token.next().unwrap().clone(),
-            };
+    // 基本となる式: 括弧、メンバーの連鎖、関数呼び出し、リテラル
+    fn call_function(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+
+        // [1, 2, 3]
+        if self.skip(Type::LBracket) {
+            let mut items = Vec::new();
+            while self.sheek_token(1) != Type::RBracket {
+                items.push(self.expression()?);
+                if !self.skip(Type::Conma) {
+                    break;
+                }
+            }
+            self.expect_err(Type::RBracket)?;
+            return Ok(Node {
+                kind: Some(NodeKind::List(items)),
+                span: self.span_from(start),
+            });
         }
-        if let Type::Number(number) = token.next().unwrap().clone() {
-            return Node {
-                kind: Some(NodeKind::Num(number as i32)),
-                token: self.now_token.next().unwrap().clone(),
-            };
+
+        // { "a": 1, "b": 2 }
+        // ブロックは body でしか読まないので、式の中の `{` は常に map になる
+        if self.skip(Type::LBraces) {
+            let mut entries = Vec::new();
+            while self.sheek_token(1) != Type::RBraces {
+                let key = self.expression()?;
+                self.expect_err(Type::Colon)?;
+                let value = self.expression()?;
+                entries.push((key, value));
+                if !self.skip(Type::Conma) {
+                    break;
+                }
+            }
+            self.expect_err(Type::RBraces)?;
+            return Ok(Node {
+                kind: Some(NodeKind::Map(entries)),
+                span: self.span_from(start),
+            });
         }
-        if let Type::Identifier(string) = self.now_token.clone().next().unwrap().clone() {
-            return Node {
-                kind: Some(NodeKind::Str(string)),
-                token: self.now_token.next().unwrap().clone(),
-            };
-        } else {
-            panic!(
-                "予想外のトークン: {:?}",
-                self.now_token.clone().next().unwrap().clone()
-            );
+
+        if self.skip(Type::LParen) {
+            let mut inner = self.expression()?;
+            self.expect_err(Type::RParen)?;
+            inner.span = self.span_from(start);
+            return Ok(inner);
         }
-    }
 
-    fn call_function(&mut self) -> Node {
-        let mut token = self.now_token.clone();
-        let function_name = match token.next().unwrap().clone() {
+        let function_name = match self.sheek_token(1) {
             Type::Identifier(word) => word,
-            _ => String::new(),
+            _ => return self.number(),
         };
-        let is_f = token.next().unwrap().clone();
-        if is_f != Type::LParen {
+        if self.sheek_token(2) != Type::LParen {
             return self.number();
         }
-        self.now_token.next();
-        let mut args = vec![Node {
-            kind: None,
-            token: Type::EOF,
-        }];
-        let kind;
-        if *self.now_token.clone().next().unwrap() == Type::LParen {
-            self.now_token.next();
-            let next_token = self.now_token.clone().next().unwrap();
-            if *next_token != Type::RParen {
-                args = self.argument();
-            }
-            self.now_token.next();
-            kind = Some(NodeKind::Call {
+        self.next_token();
+        let (args, kwargs) = self.call_arguments()?;
+
+        Ok(Node {
+            kind: Some(NodeKind::Call {
                 function_name,
                 args,
-            });
-        } else {
-            kind = Some(NodeKind::Str(function_name));
+                kwargs,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // `(1, 2, wait = True)`
+    fn call_arguments(&mut self) -> PResult<Arguments> {
+        self.expect_err(Type::LParen)?;
+        let mut arguments = (Vec::new(), Vec::new());
+        if self.sheek_token(1) != Type::RParen {
+            arguments = self.argument()?;
         }
+        self.expect_err(Type::RParen)?;
+        Ok(arguments)
+    }
+
+    // 後置の `.field` / `.method(args)` / `[index]` を左から順に付ける
+    // robot.left.run(speed * 2).angle()
+    fn postfix(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let mut target = self.call_function()?;
 
-        Node {
-            kind,
-            token: Type::EOF,
+        loop {
+            let kind = match self.sheek_token(1) {
+                Type::Period => {
+                    self.next_token();
+                    // `Port.1` のように数字の名前も許す
+                    let name = match self.sheek_token(1) {
+                        Type::Number(number) => {
+                            self.next_token();
+                            number.to_string()
+                        }
+                        _ => self.expect_identifier()?,
+                    };
+                    if self.sheek_token(1) == Type::LParen {
+                        let (args, kwargs) = self.call_arguments()?;
+                        NodeKind::MethodCall {
+                            target: Box::new(target),
+                            name,
+                            args,
+                            kwargs,
+                        }
+                    } else {
+                        NodeKind::Field {
+                            target: Box::new(target),
+                            name,
+                        }
+                    }
+                }
+                Type::LBracket => {
+                    self.next_token();
+                    let index = self.expression()?;
+                    self.expect_err(Type::RBracket)?;
+                    NodeKind::Index {
+                        target: Box::new(target),
+                        index: Box::new(index),
+                    }
+                }
+                _ => return Ok(target),
+            };
+            target = Node {
+                kind: Some(kind),
+                span: self.span_from(start),
+            };
         }
     }
 
-    fn binary_op(&mut self) -> Node {
-        let lhs = self.call_function();
-        let op;
-        let rhs;
+    // 前置演算子 `-` / `not`
+    fn unary(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let operand_precedence = match self.sheek_token(1) {
+            Type::Minus => PRECEDENCE_UNARY,
+            Type::Not => PRECEDENCE_NOT,
+            _ => return self.postfix(),
+        };
+        let op = self.next_token().token_type;
+        let operand = Box::new(self.binary_op(operand_precedence)?);
 
-        if self.now_token.clone().next().unwrap().clone() == Type::Asterisk
-            || self.now_token.clone().next().unwrap().clone() == Type::Slash
-        {
-            op = self.now_token.next().unwrap().clone();
+        Ok(Node {
+            kind: Some(NodeKind::UnaryOp { op, operand }),
+            span: self.span_from(start),
+        })
+    }
 
-            rhs = Box::new(self.binary_op());
+    // 優先順位が min_precedence 以上の二項演算子だけを左結合で読む
+    fn binary_op(&mut self, min_precedence: u8) -> PResult<Node> {
+        let start = self.now_span();
+        let mut lhs = self.unary()?;
 
-            return Node {
-                kind: Some(NodeKind::BinaryOp {
-                    op,
-                    lhs: Box::new(lhs),
-                    rhs,
-                }),
-                token: Type::EOF,
+        loop {
+            let op = self.sheek_token(1);
+            let precedence = match precedence(&op) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
             };
-        }
-        if self.now_token.clone().next().unwrap().clone() == Type::Plus
-            || self.now_token.clone().next().unwrap().clone() == Type::Minus
-        {
-            op = self.now_token.next().unwrap().clone();
+            self.next_token();
+            let rhs = Box::new(self.binary_op(precedence + 1)?);
 
-            rhs = Box::new(self.binary_op());
-
-            //
-            return Node {
-                kind: Some(NodeKind::BinaryOp {
+            let kind = match precedence {
+                PRECEDENCE_COMPARE => NodeKind::Compare {
+                    lhs: Box::new(lhs),
+                    op: Box::new(op),
+                    rhs,
+                },
+                PRECEDENCE_AND | PRECEDENCE_OR => NodeKind::Logical {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs,
+                },
+                _ => NodeKind::BinaryOp {
                     op,
                     lhs: Box::new(lhs),
                     rhs,
-                }),
-                token: Type::EOF,
+                },
+            };
+            lhs = Node {
+                kind: Some(kind),
+                span: self.span_from(start),
             };
         }
-        if type_of(self.now_token.clone().next().unwrap()) == "Type::Identifier" {
-            println!("OK");
-            lhs
-        } else {
-            lhs
-        }
+        Ok(lhs)
     }
 
-    fn err_message(&mut self, msg: String) {
-        println!("Err:{}", msg);
+    fn expression(&mut self) -> PResult<Node> {
+        self.binary_op(0)
     }
 
-    fn boolean(&mut self) -> Node {
-        let lhs = self.reserv();
-        let op;
-
-        match self.now_token.clone().next().unwrap() {
-            Type::Greater | Type::Less => {
-                op = self.now_token.next();
+    // int / list<int> / map<string, int>
+    fn type_name(&mut self) -> PResult<String> {
+        let mut name = self.expect_identifier()?;
+        if self.skip(Type::Less) {
+            let mut params = vec![self.type_name()?];
+            while self.skip(Type::Conma) {
+                params.push(self.type_name()?);
             }
-            _ => {
-                return lhs;
+            self.expect_err(Type::Greater)?;
+            name = format!("{}<{}>", name, params.join(", "));
+        }
+        Ok(name)
+    }
+
+    // `int: x` や `list<int>: xs` のような宣言の始まりか
+    fn is_declaration(&self) -> bool {
+        if !matches!(self.sheek_token(1), Type::Identifier(_)) {
+            return false;
+        }
+        let mut depth = 0;
+        let mut index = 2;
+        loop {
+            match self.sheek_token(index) {
+                Type::Colon if depth == 0 => return true,
+                Type::Less => depth += 1,
+                Type::Greater if depth > 0 => depth -= 1,
+                Type::Identifier(_) | Type::Conma if depth > 0 => {}
+                _ => return false,
             }
+            index += 1;
         }
-        let rhs = self.boolean();
+    }
 
-        Node {
-            kind: Some(NodeKind::Compare {
+    // x <- x + 1 / xs[0] <- 1
+    fn assignment(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let lhs = self.expression()?;
+        if self.sheek_token(1) != Type::Equal {
+            return Ok(lhs);
+        }
+        if !matches!(
+            lhs.kind,
+            Some(NodeKind::Str(_) | NodeKind::Index { .. } | NodeKind::Field { .. })
+        ) {
+            return Err(
+                Diagnostic::error(diagnostic::INVALID_ASSIGN_TARGET, vec![]).with_label(
+                    lhs.span,
+                    "label.invalid_assign_target",
+                    vec![],
+                ),
+            );
+        }
+        self.next_token();
+        let rhs = self.expression()?;
+
+        Ok(Node {
+            kind: Some(NodeKind::Assign {
                 lhs: Box::new(lhs),
-                op: Box::new(op.unwrap().clone()),
                 rhs: Box::new(rhs),
             }),
-            token: Type::EOF,
-        }
+            span: self.span_from(start),
+        })
     }
 
-    fn reserv(&mut self) -> Node {
-        let reserv_token = self.now_token.clone().next().unwrap();
-        let mut next_token_base = self.now_token.clone();
-        next_token_base.next();
-        let next_token = next_token_base.next().unwrap();
+    fn reserv(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let reserv_token = self.sheek_token(1);
 
-        if let Type::Period = *next_token {
-            let token = self.now_token.next().unwrap().clone();
-            let this_menber_name = self.get_identifier_contents(token);
-            self.now_token.next();
-            Node {
-                kind: Some(NodeKind::CallMenber {
-                    now_menber_name: this_menber_name.unwrap(),
-                    next: Box::new(self.reserv()),
-                }),
-                token: Type::EOF,
-            }
-        } else if let Type::Colon = *next_token {
+        if self.is_declaration() {
             // int: hoge
-            let mut v_type = String::from("");
-            let mut v_name = String::from("");
-            let mut v_formula = Box::new(Node {
-                kind: None,
-                token: Type::EOF,
-            });
+            let v_type = self.type_name()?;
+            let mut v_formula = Box::new(Node::new());
             let mut this_is_define = false;
 
-            if let Type::Identifier(word) = reserv_token {
-                v_type = word.to_string();
-            }
-            self.now_token.next();
-            self.expect_err(Type::Colon);
-            if let Type::Identifier(word) = self.now_token.next().unwrap() {
-                v_name = word.to_string();
-            }
-
-            // int: hoge = hoge
-            // TODO
+            self.expect_err(Type::Colon)?;
+            let v_name = self.expect_identifier()?;
 
-            if *self.now_token.clone().next().unwrap() == Type::Equal {
-                self.now_token.next();
-                v_formula = Box::new(self.reserv());
+            // int: hoge <- hoge
+            if self.skip(Type::Equal) {
+                v_formula = Box::new(self.expression()?);
                 this_is_define = true;
             }
 
-            Node {
+            Ok(Node {
                 kind: Some(NodeKind::Let {
                     v_name,
                     v_type,
                     v_formula,
                     this_is_define,
                 }),
-                token: Type::EOF,
-            }
-        } else if let Type::Identifier(identifier) = reserv_token.clone() {
+                span: self.span_from(start),
+            })
+        } else if let Type::Identifier(identifier) = reserv_token {
             match identifier.as_str() {
                 "return" => {
-                    self.now_token.next();
-                    let arg_node = self.reserv();
+                    self.next_token();
+                    let arg_node = self.expression()?;
 
-                    let mut now_token = self.now_token.clone();
-
-                    Node {
+                    Ok(Node {
                         kind: Some(NodeKind::Return(Box::new(arg_node))),
-                        token: now_token.next().unwrap().clone(),
-                    }
+                        span: self.span_from(start),
+                    })
                 }
                 "if" => {
                     let mut else_then: Option<Box<Node>> = None;
-                    let mut elif_then: Option<Vec<Box<Node>>> = None;
-                    self.now_token.next();
-                    let boolean = self.boolean();
-                    let then = self.body();
-                    self.skip(Type::Enter);
-
-                    if let Type::Identifier(identifier) = self.now_token.clone().next().unwrap() {
-                        match identifier.as_str() {
-                            "else" => {
-                                self.now_token.next();
-                                else_then = Some(Box::new(self.body()));
-                            }
-                            "elif" => {
-                                self.now_token.next();
-                                let boolean = self.boolean();
-                                //elif_then.push(Some(Box::new(self.body())));
-                            }
-                            _ => else_then = None,
-                        }
-                    } else {
-                        else_then = None;
+                    let mut elif_s = Vec::new();
+                    self.next_token();
+                    let boolean = self.expression()?;
+                    self.skip(Type::Equal);
+                    let then = self.body()?;
+
+                    // if / elif / elif / ... / else
+                    while self.sheek_token(1) == Type::Identifier("elif".to_string()) {
+                        let elif_start = self.now_span();
+                        self.next_token();
+                        let cond = self.expression()?;
+                        self.skip(Type::Equal);
+                        let then = self.body()?;
+                        elif_s.push(Node {
+                            kind: Some(NodeKind::Elif {
+                                cond: Box::new(cond),
+                                then: Box::new(then),
+                            }),
+                            span: self.span_from(elif_start),
+                        });
+                    }
+                    if self.sheek_token(1) == Type::Identifier("else".to_string()) {
+                        self.next_token();
+                        self.skip(Type::Equal);
+                        else_then = Some(Box::new(self.body()?));
                     }
-                    Node {
+                    let elif_then = if elif_s.is_empty() {
+                        None
+                    } else {
+                        Some(elif_s)
+                    };
+                    Ok(Node {
                         kind: Some(NodeKind::If {
                             cond: Box::new(boolean),
                             then: Box::new(then),
                             elif_then,
                             else_then,
                         }),
-                        token: Type::EOF,
-                    }
+                        span: self.span_from(start),
+                    })
                 }
-                "while" => {
-                    self.now_token.next();
-                    let boolean = self.boolean();
-                    self.expect_err(Type::Equal);
-                    let body = self.body();
-                    Node {
-                        kind: Some(NodeKind::While {
-                            cond: Box::new(boolean),
-                            body: Box::new(body),
-                        }),
-                        token: Type::EOF,
+                "while" | "for" => self.loop_statement(start, None),
+                "match" => self.match_statement(start),
+                "break" | "continue" => {
+                    self.next_token();
+                    let label = if self.skip(Type::Atsign) {
+                        Some(self.expect_identifier()?)
+                    } else {
+                        None
+                    };
+                    let span = self.span_from(start);
+
+                    if self.loop_labels.is_empty() {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                diagnostic::JUMP_OUTSIDE_LOOP,
+                                vec![identifier.clone()],
+                            )
+                            .with_label(
+                                span,
+                                "label.outside_loop",
+                                vec![identifier.clone()],
+                            ),
+                        );
+                    } else if let Some(label) = &label {
+                        if !self.loop_labels.contains(&Some(label.clone())) {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::UNDECLARED_LABEL,
+                                    vec![label.clone()],
+                                )
+                                .with_label(
+                                    span,
+                                    "label.undeclared_label",
+                                    vec![],
+                                ),
+                            );
+                        }
                     }
+
+                    let kind = if identifier == "break" {
+                        NodeKind::Break(label)
+                    } else {
+                        NodeKind::Continue(label)
+                    };
+                    Ok(Node {
+                        kind: Some(kind),
+                        span,
+                    })
                 }
                 "pass" => {
                     let word = String::from("Pass");
-                    self.now_token.next();
-                    Node {
+                    self.next_token();
+                    Ok(Node {
                         kind: Some(NodeKind::Pass(word)),
-                        token: Type::EOF,
-                    }
+                        span: self.span_from(start),
+                    })
                 }
-                _ => self.binary_op(),
+                _ => self.assignment(),
+            }
+        } else if reserv_token == Type::Atsign {
+            // @outer for i in 0..10 <- { }
+            self.next_token();
+            let label = self.expect_identifier()?;
+            match self.sheek_token(1) {
+                Type::Identifier(word) if word == "while" || word == "for" => {
+                    self.loop_statement(start, Some(label))
+                }
+                _ => Err(self.unexpected(
+                    diagnostic::UNEXPECTED_TOKEN,
+                    vec!["`while`, `for`".to_string()],
+                )),
             }
         } else {
-            self.number()
-            /*
-             */
+            self.assignment()
         }
     }
 
-    fn expr(&mut self) -> Node {
-        if let Type::Enter = self.now_token.clone().next().unwrap().clone() {
-            self.now_token.next();
-            return self.expr();
+    // match value <- { A, B <- { ... } else <- { ... } }。腕ごとにカンマ区切りの値を並べ、else は最後
+    fn match_statement(&mut self, start: Span) -> PResult<Node> {
+        self.next_token();
+        let value = self.expression()?;
+        self.expect_err(Type::Equal)?;
+        let block_start = self.now_span();
+        self.expect_err(Type::LBraces)?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while self.sheek_token(1) != Type::RBraces && self.sheek_token(1) != Type::EOF {
+            if self.skip(Type::Identifier("else".to_string())) {
+                self.skip(Type::Equal);
+                default = Some(Box::new(self.body()?));
+                continue;
+            }
+            let arm_start = self.now_span();
+            let mut patterns = vec![self.expression()?];
+            while self.skip(Type::Conma) {
+                patterns.push(self.expression()?);
+            }
+            self.expect_err(Type::Equal)?;
+            let body = self.body()?;
+            arms.push(Node {
+                kind: Some(NodeKind::MatchArm {
+                    patterns,
+                    body: Box::new(body),
+                }),
+                span: self.span_from(arm_start),
+            });
         }
-        let reserv = self.reserv();
+        self.expect_err(Type::RBraces).map_err(|diagnostic| {
+            diagnostic.with_secondary_label(block_start, "label.unclosed_block", vec![])
+        })?;
+
+        Ok(Node {
+            kind: Some(NodeKind::Match {
+                value: Box::new(value),
+                arms,
+                default,
+            }),
+            span: self.span_from(start),
+        })
+    }
 
-        if self.expect_err(Type::SemiColon) {}
+    // while cond <- { } / for i in 0..10 <- { }
+    fn loop_statement(&mut self, start: Span, label: Option<String>) -> PResult<Node> {
+        let is_for = self.next_token().token_type == Type::Identifier("for".to_string());
+        if !is_for {
+            let boolean = self.expression()?;
+            self.expect_err(Type::Equal)?;
+            let body = self.loop_body(label.clone())?;
+            return Ok(Node {
+                kind: Some(NodeKind::While {
+                    cond: Box::new(boolean),
+                    body: Box::new(body),
+                    label,
+                }),
+                span: self.span_from(start),
+            });
+        }
 
-        Node {
+        let var = self.expect_identifier()?;
+        self.expect_err(Type::Identifier("in".to_string()))?;
+        let iter_start = self.now_span();
+        let mut iter = self.expression()?;
+
+        // for i in 0..10
+        if self.skip(Type::DotDot) {
+            let end = self.expression()?;
+            iter = Node {
+                kind: Some(NodeKind::Range {
+                    start: Box::new(iter),
+                    end: Box::new(end),
+                }),
+                span: self.span_from(iter_start),
+            };
+        }
+        self.expect_err(Type::Equal)?;
+        let body = self.loop_body(label.clone())?;
+        Ok(Node {
+            kind: Some(NodeKind::For {
+                var,
+                iter: Box::new(iter),
+                body: Box::new(body),
+                label,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    fn loop_body(&mut self, label: Option<String>) -> PResult<Node> {
+        self.loop_labels.push(label);
+        let body = self.body();
+        self.loop_labels.pop();
+        body
+    }
+
+    fn expr(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let reserv = self.reserv()?;
+
+        // if / while / for / match はブロックで終わるので `;` は省略できる
+        if let Some(
+            NodeKind::If { .. }
+            | NodeKind::While { .. }
+            | NodeKind::For { .. }
+            | NodeKind::Match { .. },
+        ) = reserv.kind
+        {
+            self.skip(Type::SemiColon);
+        } else {
+            self.expect_err(Type::SemiColon)?;
+        }
+
+        Ok(Node {
             kind: Some(NodeKind::Expr {
                 reserv: Box::new(reserv),
             }),
-            token: Type::EOF,
-        }
+            span: self.span_from(start),
+        })
     }
 
-    fn body(&mut self) -> Node {
+    fn body(&mut self) -> PResult<Node> {
+        let start = self.now_span();
         let mut vec_node = Vec::new();
 
-        self.expect_err(Type::LBraces);
-        self.now_token.next();
+        self.expect_err(Type::LBraces)?;
 
-        loop {
-            self.skip(Type::Enter);
-            let mut token = self.now_token.clone();
-
-            if token.next().unwrap().clone() == Type::RBraces {
-                break;
+        while self.sheek_token(1) != Type::RBraces && self.sheek_token(1) != Type::EOF {
+            match self.expr() {
+                Ok(expr) => vec_node.push(expr),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_statement();
+                }
             }
-
-            vec_node.push(self.expr());
-            self.now_token.next();
         }
+        self.expect_err(Type::RBraces).map_err(|diagnostic| {
+            diagnostic.with_secondary_label(start, "label.unclosed_block", vec![])
+        })?;
 
-        Node {
+        Ok(Node {
             kind: Some(NodeKind::Block(vec_node)),
-            token: self.now_token.next().unwrap().clone(),
-        }
+            span: self.span_from(start),
+        })
     }
 
-    pub fn argument(&mut self) -> Vec<Node> {
+    // 呼び出しの実引数: f(1, 2, then = Stop.HOLD, wait: True)
+    pub fn argument(&mut self) -> PResult<Arguments> {
         let mut arguments: Vec<Node> = Vec::new();
+        let mut keyword_arguments: Vec<(String, Node)> = Vec::new();
         loop {
-            if let Some(next_token) = self.now_token.clone().next() {
-                if self.get_identifier_contents(next_token.clone()) == Ok("self".to_string()) {
-                    self.now_function_is_menber = true;
-                    self.now_token.next();
-                } else {
-                    let reserv = self.reserv();
+            match (self.sheek_token(1), self.sheek_token(2)) {
+                (Type::Identifier(name), Type::Equal | Type::Colon) => {
+                    self.next_token();
+                    self.next_token();
+                    let value = self.expression()?;
+                    keyword_arguments.push((name, value));
+                }
+                _ => {
+                    let reserv = self.expression()?;
+                    // キーワード引数の後に位置引数は書けない
+                    if !keyword_arguments.is_empty() {
+                        self.diagnostics.push(
+                            Diagnostic::error(diagnostic::POSITIONAL_AFTER_KEYWORD, vec![])
+                                .with_label(reserv.span, "label.positional_argument", vec![]),
+                        );
+                    }
                     arguments.push(reserv);
                 }
             }
-            let mut token = self.now_token.clone();
 
-            if token.next().unwrap().clone() == Type::Conma {
-                self.now_token.next();
-            } else {
-                //                let mut token = self.now_token.clone();
-                //                println!("OK: {:?}", token.next().unwrap().clone());
-                //                self.now_token.next();
+            if !self.skip(Type::Conma) {
                 break;
             }
         }
-        arguments
+        Ok((arguments, keyword_arguments))
     }
 
-    pub fn function(&mut self) -> Node {
-        let function_type = self.now_token.next().unwrap().clone();
+    // 関数の仮引数: self, int: a, int: b <- 0
+    pub fn parameters(&mut self) -> PResult<Vec<Node>> {
+        let mut params: Vec<Node> = Vec::new();
+        let mut has_default = false;
+        loop {
+            let start = self.now_span();
+            if self.skip(Type::Identifier("self".to_string())) {
+                self.now_function_is_menber = true;
+            } else {
+                let ty = if self.is_declaration() {
+                    let ty = self.type_name()?;
+                    self.expect_err(Type::Colon)?;
+                    Some(ty)
+                } else {
+                    None
+                };
+                let name = self.expect_identifier()?;
+                let default = if self.skip(Type::Equal) {
+                    Some(Box::new(self.expression()?))
+                } else {
+                    None
+                };
+                let span = self.span_from(start);
+
+                // 初期値のない引数は初期値のある引数より前に書く
+                if default.is_some() {
+                    has_default = true;
+                } else if has_default {
+                    self.diagnostics.push(
+                        Diagnostic::error(diagnostic::DEFAULT_PARAM_ORDER, vec![name.clone()])
+                            .with_label(span, "label.missing_default", vec![]),
+                    );
+                }
+                params.push(Node {
+                    kind: Some(NodeKind::Param { name, ty, default }),
+                    span,
+                });
+            }
 
-        if let Type::Identifier(identifier) = function_type.clone() {
-            if let "import" = identifier.as_str() {}
+            if !self.skip(Type::Conma) {
+                break;
+            }
         }
-        self.expect_err(Type::Colon);
-        let function_name = self.now_token.next().unwrap().clone();
+        Ok(params)
+    }
 
-        let mut token = self.now_token.clone();
-        let argument;
-        if token.next().unwrap() == &Type::LParen {
-            self.expect_err(Type::LParen);
-            argument = self.argument();
-            self.expect_err(Type::RParen);
-        } else {
-            argument = vec![Node {
-                kind: None,
-                token: Type::EOF,
-            }];
+    pub fn function(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let doc = self.now_doc_comment();
+        let function_type = Type::Identifier(self.type_name()?);
+
+        self.expect_err(Type::Colon)?;
+        let function_name = Type::Identifier(self.expect_identifier()?);
+
+        let mut argument = Vec::new();
+        self.now_function_is_menber = false;
+        if self.skip(Type::LParen) {
+            if self.sheek_token(1) != Type::RParen {
+                argument = self.parameters()?;
+            }
+            self.expect_err(Type::RParen)?;
         }
-        let now_function_is_menber = self.now_function_is_menber.clone();
+        let now_function_is_menber = self.now_function_is_menber;
         self.now_function_is_menber = false;
 
-        self.expect_err(Type::Equal);
+        self.expect_err(Type::Equal)?;
+        let body = Box::new(self.body()?);
 
-        Node {
+        Ok(Node {
             kind: Some(NodeKind::Function {
                 params: argument,
-                body: Box::new(self.body()),
+                body,
                 function_type,
                 function_name,
                 is_menber: now_function_is_menber,
+                doc,
             }),
-            token: Type::EOF,
-        }
+            span: self.span_from(start),
+        })
     }
 
-    pub fn class(&mut self) -> Node {
-        self.now_token.next();
-        let tmp = self.now_token.next().unwrap().clone();
-        let get_class_name = self.get_identifier_contents(tmp);
-        let mut class_name: String = "".to_string();
+    // `int: x;` / `int: x <- 0;` はフィールド、`int: f(...) <- { }` と `int: f <- { }` はメソッド
+    fn is_field(&self) -> bool {
+        if !self.is_declaration() {
+            return false;
+        }
+        let mut index = 1;
+        while self.sheek_token(index) != Type::Colon {
+            index += 1;
+        }
+        match self.sheek_token(index + 2) {
+            Type::SemiColon => true,
+            Type::Equal => {
+                self.sheek_token(index + 3) != Type::LBraces
+                    || self.is_map_literal(index + 3, self.sheek_token(1))
+            }
+            _ => false,
+        }
+    }
 
-        if let Ok(data) = get_class_name {
-            class_name = data;
-        } else if let Err(error) = get_class_name {
-            println!("Error: {}", error);
+    // index の `{` が関数の本体ではなく map のリテラルか。
+    // 本体の中の `:` は `int: x <- ...` / `int: x;` の宣言、map の `:` はキーと値の区切り。
+    // `{}` は型が map<...> のときだけ map にする
+    fn is_map_literal(&self, index: i32, v_type: Type) -> bool {
+        let mut depth = 0;
+        let mut index = index + 1;
+        loop {
+            match self.sheek_token(index) {
+                Type::RBraces if depth == 0 => {
+                    return v_type == Type::Identifier("map".to_string());
+                }
+                Type::Colon if depth == 0 => {
+                    let is_declaration = matches!(self.sheek_token(index + 1), Type::Identifier(_))
+                        && matches!(self.sheek_token(index + 2), Type::Equal | Type::SemiColon);
+                    return !is_declaration;
+                }
+                Type::SemiColon | Type::EOF => return false,
+                Type::LBraces | Type::LBracket | Type::LParen => depth += 1,
+                Type::RBraces | Type::RBracket | Type::RParen => depth -= 1,
+                _ => {}
+            }
+            index += 1;
         }
+    }
 
-        self.expect_err(Type::Equal);
-        self.expect_err(Type::LBraces);
+    fn field(&mut self) -> PResult<Node> {
+        let field = self.reserv()?;
+        self.expect_err(Type::SemiColon)?;
+        Ok(field)
+    }
+
+    pub fn class(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let doc = self.now_doc_comment();
+        self.next_token();
+        let class_name = self.expect_identifier()?;
+        let base = if self.skip(Type::Colon) {
+            let base_start = self.now_span();
+            let base = self.expect_identifier()?;
+            Some((base, self.span_from(base_start)))
+        } else {
+            None
+        };
+
+        self.expect_err(Type::Equal)?;
+        self.expect_err(Type::LBraces)?;
 
         let mut menber_s = Vec::new();
+        let mut fields = Vec::new();
 
         loop {
-            match self.enter_skip() {
-                Err("err") => {
+            match self.enter_skip(true) {
+                Err("err") | Ok("}") => {
                     break;
                 }
-                Ok("function") => {
-                    menber_s.push(self.function());
-                    if self.sheek_token(2) == Type::RBraces {
-                        break;
+                Ok(_) => {}
+                _ => continue,
+            }
+            if self.is_field() {
+                match self.field() {
+                    Ok(field) => fields.push(field),
+                    Err(diagnostic) => {
+                        self.diagnostics.push(diagnostic);
+                        self.synchronize_statement();
+                    }
+                }
+            } else {
+                match self.function() {
+                    Ok(function) => menber_s.push(function),
+                    Err(diagnostic) => {
+                        self.diagnostics.push(diagnostic);
+                        self.synchronize_declaration();
                     }
                 }
-                _ => {}
             }
-            let _ = self.enter_skip();
         }
 
-        Node {
+        Ok(Node {
             kind: Some(NodeKind::Class {
                 class_name,
+                base,
+                fields,
                 menbers: menber_s,
+                doc,
             }),
-            token: Type::EOF,
+            span: self.span_from(start),
+        })
+    }
+
+    // トップレベルの変数。`int: f <- { ... }` は関数なので、値が `{` で始まらないものだけ
+    fn global(&mut self, is_const: bool) -> PResult<Node> {
+        let start = self.now_span();
+        if is_const {
+            self.next_token();
+        }
+        let declaration = self.field()?;
+        Ok(Node {
+            kind: Some(NodeKind::Global {
+                declaration: Box::new(declaration),
+                is_const,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // enum State <- { Idle, Driving, Turning }
+    pub fn enumeration(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        let doc = self.now_doc_comment();
+        self.next_token();
+        let name = self.expect_identifier()?;
+        self.expect_err(Type::Equal)?;
+        self.expect_err(Type::LBraces)?;
+
+        let mut variants = Vec::new();
+        while self.sheek_token(1) != Type::RBraces {
+            let variant_start = self.now_span();
+            let variant = self.expect_identifier()?;
+            variants.push((variant, self.span_from(variant_start)));
+            if !self.skip(Type::Conma) {
+                break;
+            }
         }
+        self.expect_err(Type::RBraces)?;
+        self.skip(Type::SemiColon);
+
+        Ok(Node {
+            kind: Some(NodeKind::Enum {
+                name,
+                variants,
+                doc,
+            }),
+            span: self.span_from(start),
+        })
     }
 
-    pub fn import(&mut self) -> Node {
-        self.now_token.next();
-        let mut import_messod = String::from("");
-        if let Type::Identifier(import_messod_name) = self.now_token.next().unwrap() {
-            import_messod = import_messod_name.to_string();
+    // `as <名前>` があれば別名を返す
+    fn import_alias(&mut self) -> PResult<Option<String>> {
+        if self.sheek_token(1) == Type::Identifier("as".to_string()) {
+            self.next_token();
+            Ok(Some(self.expect_identifier()?))
+        } else {
+            Ok(None)
         }
-        Node {
-            kind: Some(NodeKind::Import(ToString::to_string(&import_messod))),
-            token: Type::EOF,
+    }
+
+    pub fn import(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        self.next_token();
+        let mut path = vec![self.expect_identifier()?];
+        let mut names = None;
+        while self.skip(Type::Period) {
+            if !self.skip(Type::LBraces) {
+                path.push(self.expect_identifier()?);
+                continue;
+            }
+            // `{X, Y as Z}` (最後の `,` はあってもよい)
+            let mut selected = Vec::new();
+            loop {
+                let name_start = self.now_span();
+                let name = self.expect_identifier()?;
+                let alias = self.import_alias()?;
+                selected.push((name, alias, self.span_from(name_start)));
+                if !self.skip(Type::Conma) || self.sheek_token(1) == Type::RBraces {
+                    break;
+                }
+            }
+            self.expect_err(Type::RBraces)?;
+            names = Some(selected);
+            break;
         }
+        let alias = if names.is_none() {
+            self.import_alias()?
+        } else {
+            None
+        };
+        self.skip(Type::SemiColon);
+        Ok(Node {
+            kind: Some(NodeKind::Import { path, names, alias }),
+            span: self.span_from(start),
+        })
     }
 
-    pub fn macro_raw_data(&mut self) -> Node {
-        self.now_token.next();
-        self.expect_err(Type::Colon);
-        let language_type = self.now_token.next();
-        self.expect_err(Type::Equal);
-        self.expect_err(Type::LBraces);
+    pub fn macro_raw_data(&mut self) -> PResult<Node> {
+        let start = self.now_span();
+        self.next_token();
+        self.expect_err(Type::Colon)?;
+        let language_type = match self.expect_identifier()?.as_str() {
+            "C" => RAW_DATA_TYPE_C,
+            "CPP" => RAW_DATA_TYPE_CPP,
+            "RUST" => RAW_DATA_TYPE_RUST,
+            _ => RAW_DATA_TYPE_PYTHON,
+        };
+        self.expect_err(Type::Equal)?;
+        self.expect_err(Type::LBraces)?;
 
         let mut raw_data: String = String::new();
-        self.skip(Type::Enter);
 
-        if let Type::DoubleQuotation(word) = self.now_token.clone().next().unwrap() {
-            raw_data = word.to_string();
+        if let Type::DoubleQuotation(word) = self.sheek_token(1) {
+            raw_data = word;
+            self.next_token();
         }
-        self.now_token.next();
+        self.expect_err(Type::RBraces)?;
 
-        Node {
+        Ok(Node {
             kind: Some(NodeKind::RawLanguage {
-                language_type: RAW_DATA_TYPE_PYTHON,
+                language_type,
                 raw_data,
             }),
-            token: Type::EOF,
-        }
+            span: self.span_from(start),
+        })
     }
 
-    pub fn enter_skip(&mut self) -> Result<&str, &str> {
-        let t2 = self.now_token.clone().next();
-        if t2.is_none() {
-            // Fileの終わり
-            return Err("err");
-        }
-        if let Type::RBraces = t2.unwrap() {
-            self.now_token.next();
-            return Ok("}");
-        }
-
-        if let Type::Identifier(type_or_import) = t2.unwrap() {
-            match type_or_import.as_str() {
-                "import" => Ok("import"),
-                "language" => Ok("language"),
-                "class" => Ok("class"),
-                _ => Ok("function"),
+    // 次の宣言の先頭まで進み、その種類を返す。宣言の先頭になれないトークンは
+    // 最初の 1 つだけを報告して読み飛ばす。クラスの中 (in_class) では `}` で終わる
+    pub fn enter_skip(&mut self, in_class: bool) -> Result<&str, &str> {
+        let mut skipped = None;
+        let kind = loop {
+            match self.sheek_token(1) {
+                // Fileの終わり
+                Type::EOF => break Err("err"),
+                Type::RBraces if in_class => {
+                    self.next_token();
+                    break Ok("}");
+                }
+                Type::Identifier(type_or_import) => match type_or_import.as_str() {
+                    "import" => break Ok("import"),
+                    "language" => break Ok("language"),
+                    "class" => break Ok("class"),
+                    "enum" => break Ok("enum"),
+                    "const" => break Ok("const"),
+                    _ => break Ok("function"),
+                },
+                _ => {
+                    if skipped.is_none() {
+                        let (expected, note) = if in_class {
+                            ("`}`", "note.class_body")
+                        } else {
+                            (
+                                "`import`, `class`, `enum`, `const`, `language`",
+                                "note.top_level",
+                            )
+                        };
+                        skipped = Some(
+                            self.unexpected(
+                                diagnostic::UNEXPECTED_TOKEN,
+                                vec![expected.to_string()],
+                            )
+                            .with_note(note, vec![]),
+                        );
+                    }
+                    self.next_token();
+                }
             }
-        } else {
-            self.now_token.next();
-            //let _ =
-            return self.enter_skip();
-
-            //    self.sheek_watch_token(2);
+        };
+        if let Some(diagnostic) = skipped {
+            self.diagnostics.push(diagnostic);
         }
+        kind
     }
 
-    pub fn root(&mut self) -> Node {
+    pub fn root(&mut self) -> Result<Node, Vec<Diagnostic>> {
+        let start = self.now_span();
         let mut function_define_s = Vec::new();
         self.now_token = self.tokens.iter();
 
         loop {
-            match self.enter_skip() {
-                Err("err") => {
+            let result = match self.enter_skip(false) {
+                Err(_) => {
                     break;
                 }
+                Ok("const") => self.global(true),
                 Ok("function") => {
-                    function_define_s.push(self.function());
-                }
-                Ok("class") => {
-                    function_define_s.push(self.class());
-                }
-                Ok("import") => {
-                    function_define_s.push(self.import());
+                    if self.is_field() {
+                        self.global(false)
+                    } else {
+                        self.function()
+                    }
                 }
-                Ok("language") => {
-                    function_define_s.push(self.macro_raw_data());
+                Ok("class") => self.class(),
+                Ok("enum") => self.enumeration(),
+                Ok("import") => self.import(),
+                Ok("language") => self.macro_raw_data(),
+                _ => continue,
+            };
+            match result {
+                Ok(node) => function_define_s.push(node),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_declaration();
+                    // エラーになった宣言を閉じる `}` は報告済みなので読み飛ばす
+                    self.skip(Type::RBraces);
                 }
-                _ => {}
             }
-            let _ = self.enter_skip();
         }
 
-        Node {
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+        Ok(Node {
             kind: Some(NodeKind::Root { function_define_s }),
-            token: Type::EOF,
+            span: self.span_from(start),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic;
+    use crate::token::Lexer;
+
+    fn parse(code: &str) -> Result<Node, Vec<Diagnostic>> {
+        let tokens = Lexer::new(code.to_string()).lex();
+        Parser::new(&tokens).root()
+    }
+
+    fn definitions(code: &str) -> Vec<Node> {
+        match parse(code) {
+            Ok(Node {
+                kind: Some(NodeKind::Root { function_define_s }),
+                ..
+            }) => function_define_s,
+            Ok(_) => panic!("expected a root"),
+            Err(errors) => {
+                let codes: Vec<&str> = errors.iter().map(|error| error.code).collect();
+                panic!("unexpected errors {:?} in {:?}", codes, code)
+            }
+        }
+    }
+
+    // `return <code>;` だけの関数を読み、return の式と元のコードを返す
+    fn expression(code: &str) -> (Node, String) {
+        let source = format!("int: main <- {{\n    return {};\n}}", code);
+        let function = definitions(&source).remove(0);
+        let Some(NodeKind::Function { body, .. }) = function.kind else {
+            panic!("expected a function");
+        };
+        let Some(NodeKind::Block(mut statements)) = body.kind else {
+            panic!("expected a block");
+        };
+        let mut statement = statements.remove(0);
+        if let Some(NodeKind::Expr { reserv }) = statement.kind {
+            statement = *reserv;
+        }
+        match statement.kind {
+            Some(NodeKind::Return(value)) => (*value, source),
+            _ => panic!("expected a return"),
+        }
+    }
+
+    // 式を `(+ 1 (* 2 3))` の形にする
+    fn show(node: &Node) -> String {
+        let op = |op: &Type| op.to_string().trim_matches('`').to_string();
+        match &node.kind {
+            Some(NodeKind::Num(value)) => value.to_string(),
+            Some(NodeKind::Float(value)) => format!("{:?}", value),
+            Some(NodeKind::Bool(value)) => value.to_string(),
+            Some(NodeKind::Str(name)) => name.clone(),
+            Some(NodeKind::StrLiteral(text)) => format!("{:?}", text),
+            Some(NodeKind::FormatStr(parts)) => {
+                let parts: Vec<String> = parts.iter().map(show).collect();
+                format!("(format {})", parts.join(" "))
+            }
+            Some(NodeKind::BinaryOp { op: o, lhs, rhs })
+            | Some(NodeKind::Logical { op: o, lhs, rhs }) => {
+                format!("({} {} {})", op(o), show(lhs), show(rhs))
+            }
+            Some(NodeKind::Compare { lhs, op: o, rhs }) => {
+                format!("({} {} {})", op(o), show(lhs), show(rhs))
+            }
+            Some(NodeKind::UnaryOp { op: o, operand }) => format!("({} {})", op(o), show(operand)),
+            Some(NodeKind::Call {
+                function_name,
+                args,
+                kwargs,
+            }) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(show)
+                    .chain(
+                        kwargs
+                            .iter()
+                            .map(|(name, value)| format!("{}={}", name, show(value))),
+                    )
+                    .collect();
+                format!("({} {})", function_name, args.join(" "))
+            }
+            Some(NodeKind::Field { target, name }) => format!("{}.{}", show(target), name),
+            Some(NodeKind::Index { target, index }) => format!("{}[{}]", show(target), show(index)),
+            Some(NodeKind::List(items)) => {
+                let items: Vec<String> = items.iter().map(show).collect();
+                format!("[{}]", items.join(" "))
+            }
+            _ => "?".to_string(),
         }
     }
+
+    fn assert_parses(code: &str, expected: &str) {
+        assert_eq!(show(&expression(code).0), expected, "parsing {:?}", code);
+    }
+
+    #[test]
+    fn arithmetic_precedence_and_associativity() {
+        assert_parses("1 + 2 * 3", "(+ 1 (* 2 3))");
+        assert_parses("1 * 2 + 3", "(+ (* 1 2) 3)");
+        assert_parses("1 - 2 - 3", "(- (- 1 2) 3)");
+        assert_parses("8 / 4 / 2", "(/ (/ 8 4) 2)");
+        assert_parses("(1 + 2) * 3", "(* (+ 1 2) 3)");
+        assert_parses("-a * b", "(* (- a) b)");
+    }
+
+    #[test]
+    fn comparison_and_logical_precedence() {
+        assert_parses("a + 1 < b * 2", "(< (+ a 1) (* b 2))");
+        assert_parses("a < b and c or d", "(or (and (< a b) c) d)");
+        assert_parses("a or b and c", "(or a (and b c))");
+        assert_parses("not a == b", "(not (== a b))");
+        assert_parses("not a and b", "(and (not a) b)");
+    }
+
+    #[test]
+    fn literals_calls_and_members() {
+        assert_parses("true", "true");
+        assert_parses("2.5", "2.5");
+        assert_parses("f(1, x + 1, speed = 2)", "(f 1 (+ x 1) speed=2)");
+        assert_parses("xs[i + 1].name", "xs[(+ i 1)].name");
+        assert_parses("[1, 2]", "[1 2]");
+        assert_parses(r#""n={n + 1}""#, r#"(format "n=" (+ n 1))"#);
+    }
+
+    #[test]
+    fn expression_spans_cover_their_source() {
+        let (node, source) = expression("a + b * (c - 1)");
+        let text = |node: &Node| source[node.span.start..node.span.end].to_string();
+        assert_eq!(text(&node), "a + b * (c - 1)");
+        let Some(NodeKind::BinaryOp { lhs, rhs, .. }) = &node.kind else {
+            panic!("expected a binary operation");
+        };
+        assert_eq!(text(lhs), "a");
+        assert_eq!(text(rhs), "b * (c - 1)");
+        assert_eq!((node.span.line, node.span.column), (2, 12));
+    }
+
+    #[test]
+    fn interpolated_expressions_keep_absolute_spans() {
+        let (node, source) = expression(r#""v={count}""#);
+        let Some(NodeKind::FormatStr(parts)) = &node.kind else {
+            panic!("expected a format string");
+        };
+        let span = parts[1].span;
+        assert_eq!(&source[span.start..span.end], "count");
+    }
+
+    #[test]
+    fn globals_with_map_and_list_initialisers() {
+        let definitions = definitions(
+            "map<string, int>: table <- {\"a\": 1};\nlist<int>: xs <- [1];\nint: main <- {\n    return 0;\n}\n",
+        );
+        assert_eq!(definitions.len(), 3);
+        assert!(matches!(
+            &definitions[0].kind,
+            Some(NodeKind::Global { declaration, .. })
+                if matches!(&declaration.kind, Some(NodeKind::Let { v_formula, .. })
+                    if matches!(v_formula.kind, Some(NodeKind::Map(_))))
+        ));
+        assert!(matches!(
+            &definitions[2].kind,
+            Some(NodeKind::Function { .. })
+        ));
+    }
+
+    #[test]
+    fn typed_params_with_defaults() {
+        let definitions = definitions("int: add(int: a, float: b <- 0.5) <- {\n    return a;\n}\n");
+        let Some(NodeKind::Function { params, .. }) = &definitions[0].kind else {
+            panic!("expected a function");
+        };
+        let params: Vec<(String, Option<String>, Option<String>)> = params
+            .iter()
+            .map(|param| match &param.kind {
+                Some(NodeKind::Param { name, ty, default }) => {
+                    (name.clone(), ty.clone(), default.as_deref().map(show))
+                }
+                _ => panic!("expected a parameter"),
+            })
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("a".to_string(), Some("int".to_string()), None),
+                (
+                    "b".to_string(),
+                    Some("float".to_string()),
+                    Some("0.5".to_string())
+                ),
+            ]
+        );
+
+        let errors = match parse("int: f(int: a <- 1, int: b) <- {\n    return b;\n}\n") {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+        let codes: Vec<&str> = errors.iter().map(|error| error.code).collect();
+        assert_eq!(codes, vec![diagnostic::DEFAULT_PARAM_ORDER]);
+    }
+
+    #[test]
+    fn positional_arguments_after_keywords_are_rejected() {
+        let errors = match parse("int: main <- {\n    f(a = 1, 2);\n}\n") {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+        let codes: Vec<&str> = errors.iter().map(|error| error.code).collect();
+        assert_eq!(codes, vec![diagnostic::POSITIONAL_AFTER_KEYWORD]);
+    }
+
+    #[test]
+    fn doc_comments_attach_to_the_next_definition() {
+        let definitions = definitions(
+            "/// 速さを返す\n/// 単位は mm/s\nint: speed() <- {\n    return 1;\n}\n// ただのコメント\nint: plain() <- {\n    return 0;\n}\n/// ロボット\nclass Robot <- {\n    /// 進む\n    void: go(self) <- {\n    }\n}\n",
+        );
+        let doc = |node: &Node| match &node.kind {
+            Some(NodeKind::Function { doc, .. } | NodeKind::Class { doc, .. }) => doc.clone(),
+            _ => panic!("expected a function or a class"),
+        };
+        assert_eq!(
+            doc(&definitions[0]).as_deref(),
+            Some("速さを返す\n単位は mm/s")
+        );
+        assert_eq!(doc(&definitions[1]), None);
+        assert_eq!(doc(&definitions[2]).as_deref(), Some("ロボット"));
+        let Some(NodeKind::Class { menbers, .. }) = &definitions[2].kind else {
+            panic!("expected a class");
+        };
+        assert_eq!(doc(&menbers[0]).as_deref(), Some("進む"));
+    }
+
+    #[test]
+    fn errors_are_recovered_at_the_next_declaration() {
+        let errors = match parse(
+            "int: a <- {\n    return 1 +;\n}\nint: ok <- {\n    return 0;\n}\nint: b <- {\n    x <- ;\n}\n",
+        ) {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|error| error.labels[0].span.line)
+            .collect();
+        assert_eq!(lines, vec![2, 8]);
+        assert!(errors
+            .iter()
+            .all(|error| error.code == diagnostic::EXPECTED_EXPRESSION));
+    }
+
+    #[test]
+    fn stray_tokens_between_declarations_are_reported_once() {
+        let errors = match parse("}\n5 + 3;\nint: main <- {\n    return 0;\n}\n}\n") {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|error| error.labels[0].span.line)
+            .collect();
+        assert_eq!(lines, vec![1, 6]);
+        assert!(errors
+            .iter()
+            .all(|error| error.code == diagnostic::UNEXPECTED_TOKEN));
+
+        let errors = match parse("class A <- {\n    int: x <- 1;\n    5;\n}\n") {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].labels[0].span.line, 3);
+        assert!(errors
+            .iter()
+            .all(|error| error.code == diagnostic::UNEXPECTED_TOKEN));
+    }
 }
@@ -1,33 +1,128 @@
+use crate::constant::{self, Constant};
+use crate::diagnostic::{self, Diagnostic};
+use crate::module::{Import, Role};
 use crate::parse::Node;
 use crate::parse::NodeKind;
-use crate::token::Type;
-use std::collections::HashMap;
-use std::process::exit;
+use crate::parse::RAW_DATA_TYPE_PYTHON;
+use crate::parse::{
+    constructor_params, default_value, field_assignment, insert_field_initializers,
+    CONSTRUCTOR_NAME,
+};
+use crate::parse::{
+    precedence, PRECEDENCE_ATOM, PRECEDENCE_COMPARE, PRECEDENCE_NOT, PRECEDENCE_UNARY,
+};
+use crate::resolve::Bindings;
+use crate::token::{Span, Type};
+use crate::typecheck::{Ty, Types};
+use std::collections::{HashMap, HashSet};
 
 use std::fs::File;
 use std::io::{self, Write};
 
-const CONST_VARIABLE_RESERV: i32 = 0;
-const CONST_FUNCTION_RESERV: i32 = 1;
-const CONST_CLASS_RESERV: i32 = 2;
-
 fn str_to_string(s: &str) -> String {
     s.to_string()
 }
 
+// Python の文字列リテラルの中身としてエスケープする
+fn escape_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\r' => escaped += "\\r",
+            ch if ch.is_control() => escaped += &format!("\\x{:02x}", ch as u32),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+// 代入されている変数の名前 (出てきた順)
+fn assigned_names<'a>(node: &'a Node, names: &mut Vec<&'a String>) {
+    if let Some(NodeKind::Assign { lhs, .. }) = &node.kind {
+        if let Some(NodeKind::Str(name)) = &lhs.kind {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    for child in node.children() {
+        assigned_names(child, names);
+    }
+}
+
+// 関数の中の宣言 (変数と for の変数) の名前と場所 (出てきた順)
+fn local_declarations<'a>(node: &'a Node, declarations: &mut Vec<(&'a String, Span)>) {
+    if let Some(NodeKind::Let { v_name: name, .. } | NodeKind::For { var: name, .. }) = &node.kind {
+        declarations.push((name, node.span));
+    }
+    for child in node.children() {
+        local_declarations(child, declarations);
+    }
+}
+
+// 名前を使っている場所 (変数と関数呼び出し)
+fn name_uses<'a>(node: &'a Node, uses: &mut Vec<(&'a String, Span)>) {
+    if let Some(
+        NodeKind::Str(name)
+        | NodeKind::Call {
+            function_name: name,
+            ..
+        },
+    ) = &node.kind
+    {
+        uses.push((name, node.span));
+    }
+    for child in node.children() {
+        name_uses(child, uses);
+    }
+}
+
+type Definition = (Option<Vec<Node>>, Option<Constant>);
+
 pub struct PythonGenerator {
+    pub diagnostics: Vec<Diagnostic>,
+    pub output_file: Option<String>,
+    pub role: Role,
+    // 型検査で求めた式の型
+    pub types: Types,
+    // 名前を使った場所と宣言の場所 (resolve で結びつけたもの)
+    pub bindings: Bindings,
+    // 外側の名前を隠す宣言の場所と、Python で使う名前
+    renames: HashMap<Span, String>,
     tabs_counter: i32,
     source_buf: String,
     op_preset: HashMap<Type, String>,
-    get_variable_or_function: HashMap<String, i32>,
     is_sucsess_type_test: bool,
-    now_identifier: String,
     filename: String,
+    loop_labels: Vec<Option<String>>,
+    // クラス名とコンストラクタの引数 (派生クラスのコンストラクタを作るときに使う)
+    class_constructors: HashMap<String, Vec<Node>>,
+    // `from enum import Enum` を出力したか
+    imported_enum: bool,
+    // トップレベルの変数 (関数の中で代入するときは `global` で宣言する)
+    globals: HashSet<String>,
+    constants: HashMap<String, Constant>,
+    // クラスと列挙型の名前 (型ヒントに使う)
+    type_names: HashSet<String>,
+    // import した .tf ファイルの名前と、そこから使える名前
+    modules: HashMap<String, Vec<String>>,
+    // 1つのファイルにまとめた .tf ファイルの名前
+    bundled: HashSet<String>,
 }
 
 impl PythonGenerator {
     pub fn new(filename: String) -> Self {
         Self {
+            diagnostics: Vec::new(),
+            output_file: None,
+            role: Role::Program,
+            types: HashMap::new(),
+            bindings: HashMap::new(),
+            renames: HashMap::new(),
             tabs_counter: 0,
             source_buf: str_to_string(""),
             op_preset: [
@@ -37,20 +132,171 @@ impl PythonGenerator {
                 (Type::Minus, "-"),
                 (Type::Asterisk, "*"),
                 (Type::Slash, "/"),
+                (Type::LessEqual, "<="),
+                (Type::GreaterEqual, ">="),
+                (Type::EqualEqual, "=="),
+                (Type::NotEqual, "!="),
+                (Type::And, " and "),
+                (Type::Or, " or "),
+                (Type::Not, "not "),
             ]
             .into_iter()
             .map(|(k, v)| (k, v.to_string()))
             .collect(),
-            get_variable_or_function: HashMap::new(),
             is_sucsess_type_test: true,
-            now_identifier: "".to_string(),
             filename,
+            loop_labels: Vec::new(),
+            class_constructors: HashMap::new(),
+            imported_enum: false,
+            globals: HashSet::new(),
+            constants: HashMap::new(),
+            type_names: HashSet::new(),
+            modules: HashMap::new(),
+            bundled: HashSet::new(),
+        }
+    }
+
+    // 先に生成したモジュールの名前を、import 文で選んだ名前で使えるようにする
+    pub fn import_module(&mut self, import: &Import, module: &PythonGenerator) {
+        for (name, local) in &import.names {
+            self.define(local, module.definition(name));
+            if module.type_names.contains(name) {
+                self.type_names.insert(local.clone());
+            }
+        }
+        let exports = import
+            .names
+            .iter()
+            .map(|(_, local)| local.clone())
+            .collect();
+        self.modules.insert(import.path.clone(), exports);
+    }
+
+    // 同じファイルにまとめたモジュールの import 文は、別名を付けるだけにする
+    pub fn bundle_module(&mut self, path: &str) {
+        self.bundled.insert(path.to_string());
+    }
+
+    // クラスのコンストラクタの引数と、定数の値
+    fn definition(&self, name: &str) -> Definition {
+        (
+            self.class_constructors.get(name).cloned(),
+            self.constants.get(name).cloned(),
+        )
+    }
+
+    fn define(&mut self, local: &str, (params, value): Definition) {
+        if let Some(params) = params {
+            self.class_constructors.insert(local.to_string(), params);
+        }
+        if let Some(value) = value {
+            self.constants.insert(local.to_string(), value);
+        }
+    }
+
+    // Python の変数は関数全体で1つなので、関数の中で同じ名前が別のものを指すなら宣言の名前を変える。
+    // 引数はそのまま、外側 (トップレベルや import) の名前を使っていれば宣言をすべて、
+    // そうでなければ最初の宣言以外を `x_1` のような使われていない名前にする。
+    // declarations は引数と関数の中の宣言 (引数が先)
+    fn rename_shadowing(&mut self, declarations: &[(&String, Span)], params: usize, body: &Node) {
+        let mut uses = Vec::new();
+        name_uses(body, &mut uses);
+        let mut taken: HashSet<String> = self.globals.union(&self.type_names).cloned().collect();
+        taken.extend(declarations.iter().map(|(name, _)| (*name).clone()));
+        taken.extend(uses.iter().map(|(name, _)| (*name).clone()));
+
+        let mut done = HashSet::new();
+        for (name, _) in declarations {
+            if !done.insert(*name) {
+                continue;
+            }
+            let spans: Vec<(usize, Span)> = declarations
+                .iter()
+                .enumerate()
+                .filter(|(_, (other, _))| other == name)
+                .map(|(i, (_, span))| (i, *span))
+                .collect();
+            let outer = uses.iter().any(|(other, span)| {
+                other == name
+                    && self
+                        .bindings
+                        .get(span)
+                        .is_none_or(|target| spans.iter().all(|(_, span)| span != target))
+            });
+            if spans.len() + usize::from(outer) < 2 {
+                continue;
+            }
+            let kept = match spans.first() {
+                Some((i, _)) if *i < params => spans.first().copied(),
+                _ if outer => None,
+                first => first.copied(),
+            };
+            let mut counter = 0;
+            for declaration in spans {
+                if Some(declaration) == kept {
+                    continue;
+                }
+                let renamed = loop {
+                    counter += 1;
+                    let renamed = format!("{}_{}", name, counter);
+                    if !taken.contains(&renamed) {
+                        break renamed;
+                    }
+                };
+                taken.insert(renamed.clone());
+                self.renames.insert(declaration.1, renamed);
+            }
+        }
+    }
+
+    // 宣言 (declaration) の Python での名前
+    fn local_name(&self, name: String, declaration: Span) -> String {
+        self.renames.get(&declaration).cloned().unwrap_or(name)
+    }
+
+    // 使った場所 (span) の名前を、名前を変えた宣言に合わせる
+    fn use_name(&self, name: String, span: Span) -> String {
+        match self.bindings.get(&span) {
+            Some(declaration) => self.local_name(name, *declaration),
+            None => name,
+        }
+    }
+
+    // 言語の型名を Python の型ヒントにする (`list<int>` は `list[int]`、`map<K, V>` は `dict[K, V]`)。
+    // 書けない型 (`language` ブロックのクラスなど) は None
+    fn py_type(&self, v_type: &str) -> Option<String> {
+        if let Some(element) = v_type
+            .strip_prefix("list<")
+            .and_then(|t| t.strip_suffix('>'))
+        {
+            return Some(format!("list[{}]", self.py_type(element)?));
+        }
+        if let Some((key, value)) = v_type
+            .strip_prefix("map<")
+            .and_then(|t| t.strip_suffix('>'))
+            .and_then(|t| t.split_once(", "))
+        {
+            return Some(format!(
+                "dict[{}, {}]",
+                self.py_type(key)?,
+                self.py_type(value)?
+            ));
+        }
+        match v_type {
+            "int" | "float" | "bool" => Some(v_type.to_string()),
+            "string" => Some("str".to_string()),
+            "void" => Some("None".to_string()),
+            // 後で定義されるクラスも使えるように、文字列で書く
+            _ if self.type_names.contains(v_type) => Some(format!("'{}'", v_type)),
+            _ => None,
         }
     }
 
     fn get_original_filename(&mut self, filename: String) -> String {
-        if let Some(index) = filename.find('.') {
-            filename[..index].to_string()
+        // ディレクトリ名の `.` (`../robot/main.tf` など) は拡張子ではない
+        let name_start = filename.rfind('/').map_or(0, |index| index + 1);
+        if let Some(index) = filename[name_start..].find('.') {
+            filename[..name_start + index].to_string()
         } else {
             filename.to_string()
         }
@@ -71,9 +317,6 @@ impl PythonGenerator {
     pub fn exec_argument(&mut self, params: Vec<Node>) {
         for p in &params {
             self.generator(p.clone());
-            self.get_variable_or_function
-                .insert(self.now_identifier.clone(), CONST_VARIABLE_RESERV);
-
             if Some(p) != params.last() {
                 self.add_source_buf(", ".to_string());
             } else {
@@ -82,6 +325,19 @@ impl PythonGenerator {
         }
     }
 
+    // f(a, b, then=Stop.HOLD)
+    fn exec_keyword_argument(&mut self, args: Vec<Node>, kwargs: Vec<(String, Node)>) {
+        let has_args = !args.is_empty();
+        self.exec_argument(args);
+        for (i, (name, value)) in kwargs.into_iter().enumerate() {
+            if has_args || i > 0 {
+                self.add_source_buf(", ".to_string());
+            }
+            self.add_source_buf(format!("{}=", name));
+            self.generator(value);
+        }
+    }
+
     pub fn get_identifier(&mut self, type_data: Type) -> String {
         let nothing = String::from("");
 
@@ -92,6 +348,42 @@ impl PythonGenerator {
         }
     }
 
+    // 優先順位が min_precedence より低い式は括弧で囲んで出力する
+    fn generate_operand(&mut self, node: Node, min_precedence: u8) {
+        if node.precedence() < min_precedence {
+            self.add_source_buf("(".to_string());
+            self.generator(node);
+            self.add_source_buf(")".to_string());
+        } else {
+            self.generator(node);
+        }
+    }
+
+    // `///` コメントを関数・クラス本体の先頭の docstring にする
+    fn generate_docstring(&mut self, doc: Option<String>) {
+        if let Some(doc) = doc {
+            self.tabs_counter += 1;
+            let indent = self.get_indent();
+            let doc = doc.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+            let lines: Vec<&str> = doc.lines().collect();
+            self.add_source_buf(format!("{}\"\"\"", indent));
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 && line.is_empty() {
+                    self.add_source_buf("\n".to_string());
+                } else if i > 0 {
+                    self.add_source_buf(format!("\n{}{}", indent, line));
+                } else {
+                    self.add_source_buf(line.to_string());
+                }
+            }
+            if lines.len() > 1 {
+                self.add_source_buf(format!("\n{}", indent));
+            }
+            self.add_source_buf("\"\"\"\n".to_string());
+            self.tabs_counter -= 1;
+        }
+    }
+
     pub fn get_indent(&mut self) -> String {
         let a_indent = "    ";
         let mut indent = str_to_string("");
@@ -103,70 +395,222 @@ impl PythonGenerator {
         indent
     }
 
+    // Python にはラベル付きの break / continue がないので、
+    // `_tf_jump_<ラベル>` に行き先を入れて内側のループから順に抜ける
+    fn generate_jump(&mut self, keyword: &str, label: Option<String>) {
+        match label {
+            Some(label) if self.loop_labels.last() != Some(&Some(label.clone())) => {
+                let indent = self.get_indent();
+                self.add_source_buf(format!(
+                    "_tf_jump_{} = \"{}\"\n{}break",
+                    label, keyword, indent
+                ));
+            }
+            _ => self.add_source_buf(keyword.to_string()),
+        }
+    }
+
+    fn generate_loop_body(&mut self, body: Node, label: Option<String>) {
+        if let Some(label) = &label {
+            if body.nested_jumps(label) != (false, false) {
+                self.tabs_counter += 1;
+                let indent = self.get_indent();
+                self.add_source_buf(format!("{}_tf_jump_{} = None\n", indent, label));
+                self.tabs_counter -= 1;
+            }
+        }
+        self.loop_labels.push(label);
+        self.generator(body);
+        self.loop_labels.pop();
+    }
+
+    // 内側のループを抜けた直後に、外側のラベルへの break / continue を続ける
+    fn jump_checks(&mut self, body: &Node) -> String {
+        let indent = self.get_indent();
+        let innermost = self.loop_labels.last().cloned().flatten();
+        let mut checks = String::new();
+
+        for label in self.loop_labels.iter().flatten() {
+            let (is_break, is_continue) = body.jumps_to(label);
+            if innermost.as_ref() == Some(label) {
+                for (found, keyword) in [(is_break, "break"), (is_continue, "continue")] {
+                    if found {
+                        checks += &format!(
+                            "{}if _tf_jump_{} == \"{}\":\n{}    {}\n",
+                            indent, label, keyword, indent, keyword
+                        );
+                    }
+                }
+            } else if is_break || is_continue {
+                checks += &format!(
+                    "{}if _tf_jump_{} is not None:\n{}    break\n",
+                    indent, label, indent
+                );
+            }
+        }
+        checks
+    }
+
     pub fn generator(&mut self, node: Node) {
         if let Some(node_kind) = node.kind {
             match node_kind {
                 NodeKind::Num(num) => {
                     self.add_source_buf(num.to_string());
                 }
+                NodeKind::Float(num) => {
+                    // `{:?}` なら 2.0 も `2.0` と小数点付きで出力される
+                    self.add_source_buf(format!("{:?}", num));
+                }
+                NodeKind::Bool(value) => {
+                    self.add_source_buf(if value { "True" } else { "False" }.to_string());
+                }
                 NodeKind::Str(word) => {
-                    self.now_identifier = word.clone();
+                    let word = self.use_name(word, node.span);
                     self.add_source_buf(word);
                 }
-                NodeKind::CallMenber {
-                    now_menber_name,
-                    next,
+                NodeKind::StrLiteral(text) => {
+                    self.add_source_buf(format!("\"{}\"", escape_string(&text)));
+                }
+                NodeKind::FormatStr(parts) => {
+                    // f-string では `{` `}` をそのまま書くときに二重にする
+                    self.add_source_buf("f\"".to_string());
+                    for part in parts {
+                        if let Some(NodeKind::StrLiteral(text)) = &part.kind {
+                            let text = escape_string(text).replace('{', "{{").replace('}', "}}");
+                            self.add_source_buf(text);
+                        } else {
+                            self.add_source_buf("{".to_string());
+                            self.generator(part);
+                            self.add_source_buf("}".to_string());
+                        }
+                    }
+                    self.add_source_buf("\"".to_string());
+                }
+                NodeKind::Field { target, name } => {
+                    self.generate_operand(*target, PRECEDENCE_ATOM);
+                    self.add_source_buf(format!(".{}", name));
+                }
+                NodeKind::MethodCall {
+                    target,
+                    name,
+                    args,
+                    kwargs,
                 } => {
-                    self.add_source_buf(now_menber_name);
-                    self.add_source_buf(".".to_string());
-                    self.generator(*next);
+                    if target.kind == Some(NodeKind::Str("super".to_string())) {
+                        // super._init_(x) は super().__init__(x) にする
+                        let name = if name == CONSTRUCTOR_NAME {
+                            "__init__"
+                        } else {
+                            &name
+                        };
+                        self.add_source_buf(format!("super().{}(", name));
+                    } else {
+                        self.generate_operand(*target, PRECEDENCE_ATOM);
+                        self.add_source_buf(format!(".{}(", name));
+                    }
+                    self.exec_keyword_argument(args, kwargs);
+                    self.add_source_buf(")".to_string());
                 }
                 NodeKind::Call {
                     function_name,
                     args,
+                    kwargs,
                 } => {
+                    // 名前が見つかるかどうかは resolve で調べてある
                     self.add_source_buf(function_name.clone());
-                    match self.get_variable_or_function.get(&function_name) {
-                        Some(value) => {
-                            if *value == CONST_FUNCTION_RESERV || *value == CONST_CLASS_RESERV {
-                                // 関数が使われている
-                                self.add_source_buf("(".to_string());
-                                self.exec_argument(args);
-                                self.add_source_buf(")".to_string());
-                            } else {
-                                // 変数が使われている
+                    self.add_source_buf("(".to_string());
+                    self.exec_keyword_argument(args, kwargs);
+                    self.add_source_buf(")".to_string());
+                }
+                NodeKind::Pass(_word) => self.add_source_buf("pass".to_string()),
+                NodeKind::Import { path, names, alias } => {
+                    let path = path.join(".");
+                    if self.bundled.contains(&path) {
+                        if let Some(alias) = &alias {
+                            // まとめたモジュールはモジュールとしては残らない
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::UNSUPPORTED_BY_TARGET,
+                                    vec![
+                                        format!("`import {} as {}`", path, alias),
+                                        "Python (--bundle)".to_string(),
+                                    ],
+                                )
+                                .with_label(
+                                    node.span,
+                                    "label.unsupported",
+                                    vec![],
+                                ),
+                            );
+                            self.is_sucsess_type_test = false;
+                        }
+                        for (name, local, _) in names.into_iter().flatten() {
+                            if let Some(local) = local {
+                                self.define(&local, self.definition(&name));
+                                self.add_source_buf(format!("{} = {}\n", local, name));
                             }
                         }
-                        None => {
-                            // 関数か変数かわからないものが使われている
-                            println!("警告: {}が定義されていません。直接記入モジュールに含まれていれば問題ありません。", function_name);
-
-                            self.add_source_buf("(".to_string());
-                            self.exec_argument(args);
-                            self.add_source_buf(")".to_string());
-                            //self.is_sucsess_type_test = false;
+                        return;
+                    }
+                    // .tf のモジュールは名前を直接使えるように `from ... import` にする
+                    let line = match (names, alias, self.modules.get(&path)) {
+                        (Some(names), _, _) => {
+                            let names: Vec<String> = names
+                                .into_iter()
+                                .map(|(name, local, _)| match local {
+                                    Some(local) => format!("{} as {}", name, local),
+                                    None => name,
+                                })
+                                .collect();
+                            format!("from {} import {}", path, names.join(", "))
+                        }
+                        (None, Some(alias), _) => format!("import {} as {}", path, alias),
+                        (None, None, Some(exports)) if !exports.is_empty() => {
+                            format!("from {} import {}", path, exports.join(", "))
                         }
+                        (None, None, _) => format!("import {}", path),
+                    };
+                    self.add_source_buf(line + "\n");
+                }
+                NodeKind::BinaryOp { op, lhs, rhs } => {
+                    let precedence = precedence(&op).unwrap_or(PRECEDENCE_ATOM);
+                    // int 同士の割り算は C と同じく 0 の方へ切り捨てる (`//` は負の数で切り下げになる)
+                    let is_int = |node: &Node| self.types.get(&node.span) == Some(&Ty::Int);
+                    let truncate = op == Type::Slash && is_int(&lhs) && is_int(&rhs);
+                    if truncate {
+                        self.add_source_buf("int(".to_string());
+                    }
+                    self.generate_operand(*lhs, precedence);
+                    self.add_source_buf(self.op_preset[&op].to_string());
+                    self.generate_operand(*rhs, precedence + 1);
+                    if truncate {
+                        self.add_source_buf(")".to_string());
                     }
                 }
-                NodeKind::Pass(_word) => self.add_source_buf("pass".to_string()),
-                NodeKind::Import(import_messod_name) => {
-                    self.add_source_buf("import ".to_string());
-                    self.add_source_buf(import_messod_name);
-                    self.add_source_buf("\n".to_string());
+                NodeKind::Logical { lhs, op, rhs } => {
+                    let precedence = precedence(&op).unwrap_or(PRECEDENCE_ATOM);
+                    self.generate_operand(*lhs, precedence);
+                    self.add_source_buf(self.op_preset[&op].to_string());
+                    self.generate_operand(*rhs, precedence + 1);
                 }
-                NodeKind::BinaryOp { op, lhs, rhs } => {
-                    self.generator(*lhs);
+                NodeKind::UnaryOp { op, operand } => {
                     self.add_source_buf(self.op_preset[&op].to_string());
-                    self.generator(*rhs);
+                    let precedence = if op == Type::Not {
+                        PRECEDENCE_NOT
+                    } else {
+                        PRECEDENCE_UNARY
+                    };
+                    self.generate_operand(*operand, precedence);
                 }
                 NodeKind::Return(arg) => {
                     self.add_source_buf("return ".to_string());
                     self.generator(*arg);
                 }
                 NodeKind::Compare { lhs, op, rhs } => {
-                    self.generator(*lhs);
+                    // Python の比較は連鎖するので、比較の中の比較は必ず括弧で囲む
+                    self.generate_operand(*lhs, PRECEDENCE_COMPARE + 1);
                     self.add_source_buf(self.op_preset[&*op].to_string());
-                    self.generator(*rhs);
+                    self.generate_operand(*rhs, PRECEDENCE_COMPARE + 1);
                 }
                 NodeKind::Let {
                     v_name,
@@ -174,15 +618,19 @@ impl PythonGenerator {
                     v_formula,
                     this_is_define,
                 } => {
-                    self.now_identifier = v_name.clone();
+                    let v_name = self.local_name(v_name, node.span);
                     self.add_source_buf(v_name);
                     /*
                                         self.add_source_buf(": ".to_string());
                                         self.add_source_buf(v_type);
                     */
+                    // 初期値のない list / map は空にしておく
+                    if !this_is_define && v_type.starts_with("list<") {
+                        self.add_source_buf(" = []".to_string());
+                    } else if !this_is_define && v_type.starts_with("map<") {
+                        self.add_source_buf(" = {}".to_string());
+                    }
                     if this_is_define {
-                        self.get_variable_or_function
-                            .insert(self.now_identifier.clone(), CONST_VARIABLE_RESERV);
                         self.add_source_buf(" = ".to_string());
                     }
                     self.generator(*v_formula);
@@ -197,18 +645,158 @@ impl PythonGenerator {
                     self.generator(*cond);
                     self.add_source_buf(":\n".to_string());
                     self.generator(*then);
-                    if else_then != None {
+                    for elif in elif_then.unwrap_or_default() {
+                        self.generator(elif);
+                    }
+                    if let Some(else_then) = else_then {
                         let indent = self.get_indent();
                         self.add_source_buf(indent);
                         self.add_source_buf("else:\n".to_string());
-                        self.generator(*else_then.unwrap());
+                        self.generator(*else_then);
                     }
                 }
-                NodeKind::While { cond, body } => {
+                NodeKind::Elif { cond, then } => {
+                    let indent = self.get_indent();
+                    self.add_source_buf(indent);
+                    self.add_source_buf("elif ".to_string());
+                    self.generator(*cond);
+                    self.add_source_buf(":\n".to_string());
+                    self.generator(*then);
+                }
+                NodeKind::Match {
+                    value,
+                    arms,
+                    default,
+                } => {
+                    // match は if / elif / else にする。値は一度だけ評価する
+                    let value = match value.kind {
+                        Some(NodeKind::Str(name)) => self.use_name(name, value.span),
+                        kind => {
+                            self.add_source_buf("_tf_match = ".to_string());
+                            self.generator(Node {
+                                kind,
+                                span: value.span,
+                            });
+                            let indent = self.get_indent();
+                            self.add_source_buf(format!("\n{}", indent));
+                            "_tf_match".to_string()
+                        }
+                    };
+                    let has_arms = !arms.is_empty();
+                    for (i, arm) in arms.into_iter().enumerate() {
+                        let Some(NodeKind::MatchArm { patterns, body }) = arm.kind else {
+                            continue;
+                        };
+                        if i > 0 {
+                            let indent = self.get_indent();
+                            self.add_source_buf(format!("{}elif ", indent));
+                        } else {
+                            self.add_source_buf("if ".to_string());
+                        }
+                        if patterns.len() == 1 {
+                            self.add_source_buf(format!("{} == ", value));
+                            for pattern in patterns {
+                                self.generate_operand(pattern, PRECEDENCE_COMPARE + 1);
+                            }
+                        } else {
+                            self.add_source_buf(format!("{} in (", value));
+                            for (i, pattern) in patterns.into_iter().enumerate() {
+                                if i > 0 {
+                                    self.add_source_buf(", ".to_string());
+                                }
+                                self.generator(pattern);
+                            }
+                            self.add_source_buf(")".to_string());
+                        }
+                        self.add_source_buf(":\n".to_string());
+                        self.generator(*body);
+                    }
+                    if let Some(default) = default {
+                        if has_arms {
+                            let indent = self.get_indent();
+                            self.add_source_buf(format!("{}else:\n", indent));
+                        } else {
+                            self.add_source_buf("if True:\n".to_string());
+                        }
+                        self.generator(*default);
+                    }
+                }
+                NodeKind::While { cond, body, label } => {
+                    let checks = self.jump_checks(&body);
                     self.add_source_buf("while ".to_string());
                     self.generator(*cond);
                     self.add_source_buf(":\n".to_string());
-                    self.generator(*body);
+                    self.generate_loop_body(*body, label);
+                    self.add_source_buf(checks);
+                }
+                NodeKind::For {
+                    var,
+                    iter,
+                    body,
+                    label,
+                } => {
+                    let checks = self.jump_checks(&body);
+                    let var = self.local_name(var, node.span);
+                    self.add_source_buf(format!("for {} in ", var));
+                    self.generator(*iter);
+                    self.add_source_buf(":\n".to_string());
+                    self.generate_loop_body(*body, label);
+                    self.add_source_buf(checks);
+                }
+                NodeKind::Param { name, ty, default } => {
+                    self.add_source_buf(name);
+                    let hint = ty.and_then(|ty| self.py_type(&ty));
+                    if let Some(hint) = &hint {
+                        self.add_source_buf(format!(": {}", hint));
+                    }
+                    if let Some(default) = default {
+                        // 型ヒントがあるときは `b: int = 0`、ないときは `b=0` と書く
+                        let equal = if hint.is_some() { " = " } else { "=" };
+                        self.add_source_buf(equal.to_string());
+                        self.generator(*default);
+                    }
+                }
+                NodeKind::Assign { lhs, rhs } => {
+                    self.generator(*lhs);
+                    self.add_source_buf(" = ".to_string());
+                    self.generator(*rhs);
+                }
+                NodeKind::List(items) => {
+                    self.add_source_buf("[".to_string());
+                    for (i, item) in items.into_iter().enumerate() {
+                        if i > 0 {
+                            self.add_source_buf(", ".to_string());
+                        }
+                        self.generator(item);
+                    }
+                    self.add_source_buf("]".to_string());
+                }
+                NodeKind::Map(entries) => {
+                    self.add_source_buf("{".to_string());
+                    for (i, (key, value)) in entries.into_iter().enumerate() {
+                        if i > 0 {
+                            self.add_source_buf(", ".to_string());
+                        }
+                        self.generator(key);
+                        self.add_source_buf(": ".to_string());
+                        self.generator(value);
+                    }
+                    self.add_source_buf("}".to_string());
+                }
+                NodeKind::Index { target, index } => {
+                    self.generate_operand(*target, PRECEDENCE_ATOM);
+                    self.add_source_buf("[".to_string());
+                    self.generator(*index);
+                    self.add_source_buf("]".to_string());
+                }
+                NodeKind::Break(label) => self.generate_jump("break", label),
+                NodeKind::Continue(label) => self.generate_jump("continue", label),
+                NodeKind::Range { start, end } => {
+                    self.add_source_buf("range(".to_string());
+                    self.generator(*start);
+                    self.add_source_buf(", ".to_string());
+                    self.generator(*end);
+                    self.add_source_buf(")".to_string());
                 }
                 NodeKind::Expr { reserv } => {
                     let indent = self.get_indent();
@@ -229,16 +817,39 @@ impl PythonGenerator {
                     function_type,
                     function_name,
                     is_menber,
+                    doc,
                 } => {
+                    let mut declarations = Vec::new();
+                    for param in &params {
+                        if let Some(NodeKind::Param { name, .. }) = &param.kind {
+                            declarations.push((name, param.span));
+                        }
+                    }
+                    let param_count = declarations.len();
+                    local_declarations(&body, &mut declarations);
+                    self.rename_shadowing(&declarations, param_count, &body);
+
+                    // 関数の中で宣言していないトップレベルの変数に代入するなら `global` が要る
+                    let mut assigned = Vec::new();
+                    assigned_names(&body, &mut assigned);
+                    let locals: HashSet<&String> = declarations
+                        .iter()
+                        .filter(|(_, span)| !self.renames.contains_key(span))
+                        .map(|(name, _)| *name)
+                        .collect();
+                    let global_names: Vec<String> = assigned
+                        .into_iter()
+                        .filter(|name| self.globals.contains(*name) && !locals.contains(name))
+                        .cloned()
+                        .collect();
+
                     let identifier = self.get_identifier(function_name);
-                    self.get_variable_or_function
-                        .insert(identifier.to_string(), CONST_FUNCTION_RESERV);
                     let f_type = self.get_identifier(function_type);
                     let indent = self.get_indent();
                     self.add_source_buf(indent);
                     self.add_source_buf("def ".to_string());
 
-                    if identifier == "_init_".to_string() {
+                    if identifier == CONSTRUCTOR_NAME {
                         self.add_source_buf("__init__".to_string());
 
                         self.add_source_buf("(".to_string());
@@ -263,22 +874,93 @@ impl PythonGenerator {
                         }
 
                         self.exec_argument(params);
-                        self.add_source_buf(") -> ".to_string());
-                        self.add_source_buf(f_type);
+                        self.add_source_buf(")".to_string());
+                        if let Some(f_type) = self.py_type(&f_type) {
+                            self.add_source_buf(format!(" -> {}", f_type));
+                        }
                     }
                     self.add_source_buf(":\n".to_string());
+                    self.generate_docstring(doc);
+                    if !global_names.is_empty() {
+                        self.tabs_counter += 1;
+                        let indent = self.get_indent();
+                        self.tabs_counter -= 1;
+                        self.add_source_buf(format!(
+                            "{}global {}\n",
+                            indent,
+                            global_names.join(", ")
+                        ));
+                    }
                     self.generator(*body);
                     self.add_source_buf("\n\n".to_string());
                 }
                 NodeKind::Class {
                     class_name,
+                    base,
+                    fields,
                     menbers,
+                    doc,
                 } => {
-                    self.get_variable_or_function
-                        .insert(class_name.to_string(), CONST_CLASS_RESERV);
+                    // フィールドは __init__ の中で self.x = ... として作る
+                    let initializers = fields
+                        .into_iter()
+                        .filter_map(|field| match field.kind {
+                            Some(NodeKind::Let {
+                                v_name,
+                                v_type,
+                                v_formula,
+                                this_is_define,
+                            }) => {
+                                let value = if this_is_define {
+                                    *v_formula
+                                } else if let Some(value) = default_value(&v_type, field.span) {
+                                    value
+                                } else if v_type.starts_with("list<") {
+                                    Node {
+                                        kind: Some(NodeKind::List(Vec::new())),
+                                        span: field.span,
+                                    }
+                                } else if v_type.starts_with("map<") {
+                                    Node {
+                                        kind: Some(NodeKind::Map(Vec::new())),
+                                        span: field.span,
+                                    }
+                                } else {
+                                    Node {
+                                        kind: Some(NodeKind::Str("None".to_string())),
+                                        span: field.span,
+                                    }
+                                };
+                                Some(field_assignment(&v_name, value))
+                            }
+                            _ => None,
+                        })
+                        .collect::<Vec<Node>>();
+                    // 基底クラスのコンストラクタの引数は、作るコンストラクタにも引き継ぐ
+                    let inherited = base.as_ref().map(|(base, _)| {
+                        self.class_constructors
+                            .get(base)
+                            .cloned()
+                            .unwrap_or_default()
+                    });
+                    let menbers = if initializers.is_empty() {
+                        menbers
+                    } else {
+                        insert_field_initializers(menbers, initializers, inherited.clone())
+                    };
+                    self.class_constructors.insert(
+                        class_name.clone(),
+                        constructor_params(&menbers)
+                            .or(inherited)
+                            .unwrap_or_default(),
+                    );
                     self.add_source_buf("class ".to_string());
                     self.add_source_buf(class_name);
+                    if let Some((base, _)) = base {
+                        self.add_source_buf(format!("({})", base));
+                    }
                     self.add_source_buf(":\n".to_string());
+                    self.generate_docstring(doc);
                     self.tabs_counter += 1;
                     for func in menbers {
                         self.generator(func);
@@ -286,27 +968,124 @@ impl PythonGenerator {
                     self.tabs_counter -= 1;
                 }
 
+                NodeKind::Global {
+                    declaration,
+                    is_const,
+                } => {
+                    let Some(NodeKind::Let {
+                        v_name,
+                        v_type,
+                        v_formula,
+                        this_is_define,
+                    }) = declaration.kind
+                    else {
+                        return;
+                    };
+                    // 定数はコンパイル時に計算した値にする。初期値がなければ C と同じく 0 にする
+                    let value = if !this_is_define {
+                        default_value(&v_type, node.span).unwrap_or_else(|| {
+                            let kind = if v_type.starts_with("list<") {
+                                NodeKind::List(Vec::new())
+                            } else if v_type.starts_with("map<") {
+                                NodeKind::Map(Vec::new())
+                            } else {
+                                NodeKind::Str("None".to_string())
+                            };
+                            Node {
+                                kind: Some(kind),
+                                span: node.span,
+                            }
+                        })
+                    } else if is_const {
+                        match constant::evaluate(&v_formula, &self.constants) {
+                            Ok(value) => {
+                                let folded = value.to_node(v_formula.span);
+                                self.constants.insert(v_name.clone(), value);
+                                folded
+                            }
+                            Err(_) => *v_formula,
+                        }
+                    } else {
+                        *v_formula
+                    };
+                    self.add_source_buf(format!("{} = ", v_name));
+                    self.generator(value);
+                    self.add_source_buf("\n".to_string());
+                }
+                NodeKind::Enum {
+                    name,
+                    variants,
+                    doc,
+                } => {
+                    // 列挙型は Enum を継承したクラスにする (値は C の enum と同じく 0 から)
+                    if !self.imported_enum {
+                        self.imported_enum = true;
+                        self.add_source_buf("from enum import Enum\n\n\n".to_string());
+                    }
+                    self.add_source_buf(format!("class {}(Enum):\n", name));
+                    self.generate_docstring(doc);
+                    for (i, (variant, _)) in variants.into_iter().enumerate() {
+                        self.add_source_buf(format!("    {} = {}\n", variant, i));
+                    }
+                    self.add_source_buf("\n\n".to_string());
+                }
                 NodeKind::RawLanguage {
                     language_type,
                     raw_data,
-                } => {
+                } if language_type == RAW_DATA_TYPE_PYTHON => {
                     self.add_source_buf(raw_data);
                 }
                 NodeKind::Root { function_define_s } => {
+                    for ast in &function_define_s {
+                        if let Some(
+                            NodeKind::Class {
+                                class_name: name, ..
+                            }
+                            | NodeKind::Enum { name, .. },
+                        ) = &ast.kind
+                        {
+                            self.type_names.insert(name.clone());
+                        }
+                        if let Some(NodeKind::Global {
+                            declaration,
+                            is_const: false,
+                        }) = &ast.kind
+                        {
+                            if let Some(NodeKind::Let { v_name, .. }) = &declaration.kind {
+                                self.globals.insert(v_name.clone());
+                            }
+                        }
+                    }
+                    let mut after_global = false;
                     for ast in function_define_s {
+                        // import とトップレベルの変数の並びと、その後の定義の間は2行空ける
+                        let is_global = matches!(
+                            ast.kind,
+                            Some(NodeKind::Global { .. } | NodeKind::Import { .. })
+                        );
+                        if after_global && !is_global {
+                            self.add_source_buf("\n\n".to_string());
+                        }
+                        after_global = is_global;
                         self.generator(ast);
                     }
-                    if !self.is_sucsess_type_test {
-                        exit(0);
+                    if !self.is_sucsess_type_test || self.role == Role::Bundled {
+                        return;
+                    }
+                    // import されるファイルは main を呼ばない
+                    if self.role == Role::Program {
+                        self.add_source_buf("main()".to_string());
                     }
-                    self.add_source_buf("main()".to_string());
                     let mut filename = self.filename.clone();
                     filename = self.get_original_filename(filename) + ".py";
                     let buf: &str = &self.source_buf.clone();
                     if let Err(e) = self.write_to_file(filename.clone(), buf) {
-                        eprintln!("Error: {}", e);
+                        self.diagnostics.push(Diagnostic::error(
+                            diagnostic::OUTPUT_WRITE_FAILED,
+                            vec![filename, e.to_string()],
+                        ));
                     } else {
-                        println!("File '{}' created and written successfully.", filename);
+                        self.output_file = Some(filename);
                     }
                 }
                 _ => {}
@@ -0,0 +1,396 @@
+use crate::diagnostic::{self, Diagnostic, Severity};
+use crate::module::Import;
+use crate::parse::{Node, NodeKind};
+use crate::token::{Span, Type};
+use std::collections::HashMap;
+
+// 名前を使った場所と、その名前を宣言した場所
+pub type Bindings = HashMap<Span, Span>;
+
+// 宣言しなくても使える関数
+const BUILTINS: [&str; 1] = ["print"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScopeKind {
+    Module,
+    // フィールドとメソッドは `self.` を付けて使うので、名前を探すときは飛ばす
+    Class,
+    Function,
+    Block,
+}
+
+struct Scope {
+    kind: ScopeKind,
+    // 名前と宣言の場所 (import したものや self は None)
+    names: HashMap<String, Option<Span>>,
+}
+
+struct Resolver<'a> {
+    scopes: Vec<Scope>,
+    // .tf ファイルを読み込んだ import 文
+    imports: &'a [Import],
+    // 見つからない名前を Error と Warning のどちらで報告するか
+    undefined: Severity,
+    bindings: Bindings,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver<'_> {
+    fn push(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            kind,
+            names: HashMap::new(),
+        });
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    // 内側のスコープから順に探し、宣言の場所を返す
+    fn lookup(&self, name: &str) -> Option<Option<Span>> {
+        self.scopes
+            .iter()
+            .rev()
+            .filter(|scope| scope.kind != ScopeKind::Class)
+            .find_map(|scope| scope.names.get(name).copied())
+    }
+
+    fn declare(&mut self, name: &str, span: Option<Span>) {
+        let scope = self.scopes.last().expect("a scope is always open");
+        if let Some(previous) = scope.names.get(name).copied() {
+            // import したもの同士は同じ名前を何度 import してもよい
+            if let Some(span) = span {
+                let diagnostic =
+                    Diagnostic::error(diagnostic::DUPLICATE_DEFINITION, vec![name.to_string()])
+                        .with_label(span, "label.duplicate_definition", vec![]);
+                self.diagnostics.push(with_previous(diagnostic, previous));
+            }
+            return;
+        }
+        // 隠すのは関数とブロックの中の宣言だけ (フィールドとメソッドは `self.` で区別できる)
+        let is_local = matches!(scope.kind, ScopeKind::Function | ScopeKind::Block);
+        if let (Some(span), true) = (span, is_local) {
+            if let Some(previous) = self.lookup(name) {
+                let diagnostic =
+                    Diagnostic::warning(diagnostic::SHADOWED_NAME, vec![name.to_string()])
+                        .with_label(span, "label.shadows", vec![]);
+                self.diagnostics.push(with_previous(diagnostic, previous));
+            }
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name.to_string(), span);
+        }
+    }
+
+    fn use_name(&mut self, name: &str, span: Span) {
+        if let Some(declaration) = self.lookup(name) {
+            // import したものや self は、このファイルに宣言がない
+            if let Some(declaration) = declaration {
+                self.bindings.insert(span, declaration);
+            }
+            return;
+        }
+        if BUILTINS.contains(&name) {
+            return;
+        }
+        let mut diagnostic = Diagnostic::new(
+            self.undefined,
+            diagnostic::UNDEFINED_NAME,
+            vec![name.to_string()],
+        )
+        .with_label(span, "label.undefined", vec![]);
+        if self.undefined == Severity::Warning {
+            diagnostic = diagnostic.with_note("note.raw_module", vec![]);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    // import 文で使えるようになる名前 (.tf 以外のモジュールは Python の import と同じ)
+    fn import(
+        &mut self,
+        path: &[String],
+        names: &Option<Vec<(String, Option<String>, Span)>>,
+        alias: &Option<String>,
+    ) {
+        let joined = path.join(".");
+        if let Some(import) = self.imports.iter().find(|import| import.path == joined) {
+            for (_, local) in &import.names {
+                self.declare(local, None);
+            }
+            if let Some(alias) = alias {
+                self.declare(alias, None);
+            }
+            return;
+        }
+        match (names, alias) {
+            (Some(names), _) => {
+                for (name, local, _) in names {
+                    self.declare(local.as_ref().unwrap_or(name), None);
+                }
+            }
+            (None, Some(alias)) => self.declare(alias, None),
+            // `import a.b` で使えるようになるのは `a`
+            (None, None) => self.declare(&path[0], None),
+        }
+    }
+
+    fn root(&mut self, definitions: &[Node]) {
+        self.push(ScopeKind::Module);
+        // 関数やクラスは使う場所より後で定義されていてもよいので、先に全部宣言する
+        for node in definitions {
+            match &node.kind {
+                Some(NodeKind::Import { path, names, alias }) => self.import(path, names, alias),
+                Some(NodeKind::Function {
+                    function_name: Type::Identifier(name),
+                    ..
+                }) => self.declare(name, Some(node.span)),
+                Some(NodeKind::Class { class_name, .. }) => {
+                    self.declare(class_name, Some(node.span))
+                }
+                Some(NodeKind::Enum { name, .. }) => self.declare(name, Some(node.span)),
+                Some(NodeKind::Global { declaration, .. }) => {
+                    if let Some(NodeKind::Let { v_name, .. }) = &declaration.kind {
+                        self.declare(v_name, Some(declaration.span));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for node in definitions {
+            match &node.kind {
+                Some(NodeKind::Function { .. }) => self.function(node, false),
+                Some(NodeKind::Class {
+                    fields, menbers, ..
+                }) => {
+                    self.push(ScopeKind::Class);
+                    for field in fields {
+                        if let Some(NodeKind::Let {
+                            v_name,
+                            v_formula,
+                            this_is_define,
+                            ..
+                        }) = &field.kind
+                        {
+                            if *this_is_define {
+                                self.expression(v_formula);
+                            }
+                            self.declare(v_name, Some(field.span));
+                        }
+                    }
+                    for menber in menbers {
+                        if let Some(NodeKind::Function {
+                            function_name: Type::Identifier(name),
+                            ..
+                        }) = &menber.kind
+                        {
+                            self.declare(name, Some(menber.span));
+                        }
+                    }
+                    for menber in menbers {
+                        self.function(menber, true);
+                    }
+                    self.pop();
+                }
+                Some(NodeKind::Global { declaration, .. }) => {
+                    if let Some(NodeKind::Let {
+                        v_formula,
+                        this_is_define: true,
+                        ..
+                    }) = &declaration.kind
+                    {
+                        self.expression(v_formula);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.pop();
+    }
+
+    fn function(&mut self, node: &Node, is_method: bool) {
+        let Some(NodeKind::Function { params, body, .. }) = &node.kind else {
+            return;
+        };
+        self.push(ScopeKind::Function);
+        if is_method {
+            self.declare("self", None);
+            self.declare("super", None);
+        }
+        for param in params {
+            if let Some(NodeKind::Param { name, default, .. }) = &param.kind {
+                if let Some(default) = default {
+                    self.expression(default);
+                }
+                self.declare(name, Some(param.span));
+            }
+        }
+        // 関数の本体は引数と同じスコープ
+        self.statements(body);
+        self.pop();
+    }
+
+    // ブロックの中身を、今のスコープで解決する
+    fn statements(&mut self, node: &Node) {
+        match &node.kind {
+            Some(NodeKind::Block(statements)) => {
+                for statement in statements {
+                    self.expression(statement);
+                }
+            }
+            _ => self.expression(node),
+        }
+    }
+
+    fn expression(&mut self, node: &Node) {
+        match &node.kind {
+            Some(NodeKind::Str(name)) => self.use_name(name, node.span),
+            Some(NodeKind::Call { function_name, .. }) => {
+                self.use_name(function_name, node.span);
+                for child in node.children() {
+                    self.expression(child);
+                }
+            }
+            Some(NodeKind::Let {
+                v_name,
+                v_formula,
+                this_is_define,
+                ..
+            }) => {
+                // `int: x <- x + 1;` の右辺の x は外側のもの
+                if *this_is_define {
+                    self.expression(v_formula);
+                }
+                self.declare(v_name, Some(node.span));
+            }
+            Some(NodeKind::Block(_)) => {
+                self.push(ScopeKind::Block);
+                self.statements(node);
+                self.pop();
+            }
+            Some(NodeKind::For {
+                var, iter, body, ..
+            }) => {
+                self.expression(iter);
+                self.push(ScopeKind::Block);
+                self.declare(var, Some(node.span));
+                self.statements(body);
+                self.pop();
+            }
+            _ => {
+                for child in node.children() {
+                    self.expression(child);
+                }
+            }
+        }
+    }
+}
+
+// 前の宣言がこのファイルにあれば指す
+fn with_previous(diagnostic: Diagnostic, previous: Option<Span>) -> Diagnostic {
+    match previous {
+        Some(span) => diagnostic.with_secondary_label(span, "label.previous_definition", vec![]),
+        None => diagnostic,
+    }
+}
+
+// 名前をすべて宣言に結びつけ、見つからない名前、同じスコープでの二重定義、外側の名前を隠す宣言を報告する。
+// imports は .tf ファイルを読み込んだ import 文、undefined は見つからない名前の重さ
+pub fn resolve(
+    root: &Node,
+    imports: &[Import],
+    undefined: Severity,
+) -> (Vec<Diagnostic>, Bindings) {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        imports,
+        undefined,
+        bindings: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    if let Some(NodeKind::Root { function_define_s }) = &root.kind {
+        resolver.root(function_define_s);
+    }
+    (resolver.diagnostics, resolver.bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+    use crate::token::Lexer;
+
+    fn resolve_code(
+        code: &str,
+        imports: &[Import],
+        undefined: Severity,
+    ) -> (Vec<Diagnostic>, Bindings) {
+        let tokens = Lexer::new(code.to_string()).lex();
+        let Ok(root) = Parser::new(&tokens).root() else {
+            panic!("failed to parse {:?}", code);
+        };
+        resolve(&root, imports, undefined)
+    }
+
+    // 見つかったもののコードと重さと行
+    fn reported(diagnostics: &[Diagnostic]) -> Vec<(&'static str, Severity, usize)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let line = diagnostic.labels[0].span.line;
+                (diagnostic.code, diagnostic.severity, line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn uses_bind_to_the_innermost_declaration() {
+        let code = "int: main <- {\n    int: k <- helper();\n    if k > 0 <- {\n        int: k <- 2;\n        print(k);\n    }\n    for i in 0..k <- {\n        print(i);\n    }\n    return k;\n}\nint: helper() <- {\n    return 1;\n}\n";
+        let (diagnostics, bindings) = resolve_code(code, &[], Severity::Error);
+        assert_eq!(
+            reported(&diagnostics),
+            vec![(diagnostic::SHADOWED_NAME, Severity::Warning, 4)]
+        );
+        // 使った行と宣言の行
+        let mut lines: Vec<(usize, usize)> = bindings
+            .iter()
+            .map(|(used, declared)| (used.line, declared.line))
+            .collect();
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            vec![(2, 12), (3, 2), (5, 4), (7, 2), (8, 7), (10, 2)]
+        );
+    }
+
+    #[test]
+    fn duplicates_and_undefined_names() {
+        let code = "int: a() <- {\n    int: x <- 1;\n    int: x <- 2;\n    return x;\n}\nint: b() <- {\n    return x + go();\n}\n";
+        let (diagnostics, _) = resolve_code(code, &[], Severity::Error);
+        assert_eq!(
+            reported(&diagnostics),
+            vec![
+                (diagnostic::DUPLICATE_DEFINITION, Severity::Error, 3),
+                (diagnostic::UNDEFINED_NAME, Severity::Error, 7),
+                (diagnostic::UNDEFINED_NAME, Severity::Error, 7),
+            ]
+        );
+
+        // import した名前は宣言がなくても使え、見つからない名前は警告にもできる
+        let imports = [Import {
+            path: "drive".to_string(),
+            names: vec![("forward".to_string(), "go".to_string())],
+            module: 0,
+        }];
+        let code = format!("import drive.{{forward as go}};\n{}", code);
+        let (diagnostics, bindings) = resolve_code(&code, &imports, Severity::Warning);
+        assert_eq!(
+            reported(&diagnostics),
+            vec![
+                (diagnostic::DUPLICATE_DEFINITION, Severity::Error, 4),
+                (diagnostic::UNDEFINED_NAME, Severity::Warning, 8),
+            ]
+        );
+        assert!(bindings.keys().all(|used| used.line != 8));
+    }
+}
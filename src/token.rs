@@ -1,9 +1,12 @@
+use crate::diagnostic::{self, Diagnostic};
 use maplit::hashmap;
 use std::collections::HashMap;
-use std::iter::Peekable;
-use std::str::Chars;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
 
-#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Type {
     Plus,
     Minus,
@@ -16,28 +19,155 @@ pub enum Type {
     Equal,
     Less,
     Greater,
-    Enter,
     LBraces,
     RBraces,
+    LBracket,
+    RBracket,
     Conma,
     Period,
+    DotDot,
+    EqualEqual,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    And,
+    Or,
+    Not,
     Identifier(String),
     DoubleQuotation(String),
+    Template(Vec<TemplatePart>),
     Number(i64),
+    Float(f64),
     Hashtag,
     Atsign,
     EOF,
 }
 
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Plus => write!(f, "`+`"),
+            Type::Minus => write!(f, "`-`"),
+            Type::Asterisk => write!(f, "`*`"),
+            Type::Slash => write!(f, "`/`"),
+            Type::LParen => write!(f, "`(`"),
+            Type::RParen => write!(f, "`)`"),
+            Type::SemiColon => write!(f, "`;`"),
+            Type::Colon => write!(f, "`:`"),
+            Type::Equal => write!(f, "`<-`"),
+            Type::Less => write!(f, "`<`"),
+            Type::Greater => write!(f, "`>`"),
+            Type::LBraces => write!(f, "`{{`"),
+            Type::RBraces => write!(f, "`}}`"),
+            Type::LBracket => write!(f, "`[`"),
+            Type::RBracket => write!(f, "`]`"),
+            Type::Conma => write!(f, "`,`"),
+            Type::Period => write!(f, "`.`"),
+            Type::DotDot => write!(f, "`..`"),
+            Type::EqualEqual => write!(f, "`==`"),
+            Type::NotEqual => write!(f, "`!=`"),
+            Type::LessEqual => write!(f, "`<=`"),
+            Type::GreaterEqual => write!(f, "`>=`"),
+            Type::And => write!(f, "`and`"),
+            Type::Or => write!(f, "`or`"),
+            Type::Not => write!(f, "`not`"),
+            Type::Identifier(word) => write!(f, "`{}`", word),
+            Type::DoubleQuotation(word) => write!(f, "`\"{}\"`", word),
+            Type::Template(parts) => {
+                write!(f, "`\"")?;
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => write!(f, "{}", text)?,
+                        TemplatePart::Code(_) => write!(f, "{{...}}")?,
+                    }
+                }
+                write!(f, "\"`")
+            }
+            Type::Number(number) => write!(f, "`{}`", number),
+            Type::Float(number) => write!(f, "`{:?}`", number),
+            Type::Hashtag => write!(f, "`#`"),
+            Type::Atsign => write!(f, "`@`"),
+            Type::EOF => write!(f, "EOF"),
+        }
+    }
+}
+
+// 浮動小数点数のリテラルは NaN にならないので、ビット列で比較・ハッシュしてよい
+impl Eq for Type {}
+
+impl Hash for Type {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Type::Identifier(word) | Type::DoubleQuotation(word) => word.hash(state),
+            Type::Template(parts) => parts.hash(state),
+            Type::Number(number) => number.hash(state),
+            Type::Float(number) => number.to_bits().hash(state),
+            _ => {}
+        }
+    }
+}
+
+// ソース上の位置 (byte offset は半開区間、line / column は 1 始まり)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    // self の先頭から other の末尾までを覆う Span
+    pub fn to(self, other: Span) -> Span {
+        if other.end <= self.start {
+            return self;
+        }
+        Span {
+            start: self.start,
+            end: other.end,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+// 埋め込み式を含む文字列 `"speed={s}"` の各部分
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TemplatePart {
+    Text(String),
+    // `{` と `}` の間のトークン列 (最後は EOF)
+    Code(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Token {
-    token_content: String,
-    token_type: Type,
+    pub token_content: String,
+    pub token_type: Type,
+    pub span: Span,
+    // 直前の `///` コメント (関数やクラスの説明として使う)
+    pub doc_comment: Option<String>,
 }
 
 pub struct Lexer {
+    pub diagnostics: Vec<Diagnostic>,
     tokens: Vec<Token>,
     code: String,
     char_to_type: HashMap<char, Type>,
+    str_to_type: HashMap<&'static str, Type>,
+    doc_comment: Vec<String>,
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
 impl Lexer {
@@ -57,86 +187,309 @@ impl Lexer {
             '>' => Type::Greater,
             '{' => Type::LBraces,
             '}' => Type::RBraces,
+            '[' => Type::LBracket,
+            ']' => Type::RBracket,
             ',' => Type::Conma,
             '.' => Type::Period,
             '#' => Type::Hashtag,
-            '\n' => Type::Enter,
             '@' => Type::Atsign,
-            '!' => Type::EOF,
+            '!' => Type::Not,
+        );
+        // 2文字の演算子 (1文字の演算子より優先する)
+        let operators_hash = hashmap!(
+            "<-" => Type::Equal,
+            "==" => Type::EqualEqual,
+            "!=" => Type::NotEqual,
+            "<=" => Type::LessEqual,
+            ">=" => Type::GreaterEqual,
+            "&&" => Type::And,
+            "||" => Type::Or,
+            ".." => Type::DotDot,
         );
         Self {
+            diagnostics: Vec::new(),
             tokens,
             code,
             char_to_type: types_hash,
+            str_to_type: operators_hash,
+            doc_comment: Vec::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
         }
     }
 
-    pub fn lex(&mut self) -> Vec<Type> {
-        let mut tokens = vec![];
-        let chars = &mut self.code.clone();
-        let mut chars = chars.chars().peekable();
+    fn peek_char(&self) -> Option<char> {
+        self.code[self.offset..].chars().next()
+    }
 
-        while let Some(&ch) = chars.peek() {
-            if ch == '<' {
-                let mut chars2 = chars.clone();
-                chars2.next();
-                let ch2 = chars2.next();
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek_char()?;
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
 
-                if ch2.unwrap() == '-' {
-                    tokens.push(Type::Equal);
-                    chars.next();
-                } else {
-                    tokens.push(Type::Less);
-                }
-                chars.next();
+    // 現在位置から始まる Span (end は push_token で確定する)
+    fn start_span(&self) -> Span {
+        Span::new(self.offset, self.offset, self.line, self.column)
+    }
+
+    fn push_token(&mut self, token_type: Type, mut span: Span) {
+        span.end = self.offset;
+        let doc_comment = if self.doc_comment.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.doc_comment).join("\n"))
+        };
+        self.tokens.push(Token {
+            token_content: self.code[span.start..span.end].to_string(),
+            token_type,
+            span,
+            doc_comment,
+        });
+    }
+
+    // `//` から行末まで読み飛ばす。`///` なら次のトークンの説明として残す
+    fn skip_line_comment(&mut self) {
+        let is_doc = self.code[self.offset..].starts_with("///");
+        let mut comment = String::new();
+        while let Some(ch) = self.peek_char() {
+            if ch == '\n' {
+                break;
+            }
+            comment.push(ch);
+            self.next_char();
+        }
+        if is_doc {
+            let line = comment.trim_start_matches('/');
+            let line = line.strip_prefix(' ').unwrap_or(line);
+            self.doc_comment.push(line.trim_end().to_string());
+        }
+    }
+
+    // `/*` から `*/` まで読み飛ばす
+    fn skip_block_comment(&mut self, mut span: Span) {
+        self.next_char();
+        self.next_char();
+        while !self.code[self.offset..].starts_with("*/") {
+            if self.next_char().is_none() {
+                span.end = span.start + 2;
+                self.diagnostics.push(
+                    Diagnostic::error(diagnostic::UNTERMINATED_COMMENT, vec![]).with_label(
+                        span,
+                        "label.comment_start",
+                        vec![],
+                    ),
+                );
+                return;
+            }
+        }
+        self.next_char();
+        self.next_char();
+    }
+
+    pub fn lex(&mut self) -> Vec<Token> {
+        while self.peek_char().is_some() {
+            self.lex_token();
+        }
+        let span = self.start_span();
+        self.push_token(Type::EOF, span);
+
+        std::mem::take(&mut self.tokens)
+    }
+
+    // トークンを1つ読む (空白は読み飛ばすだけ)
+    fn lex_token(&mut self) {
+        if let Some(ch) = self.peek_char() {
+            let span = self.start_span();
+
+            let pair: String = self.code[self.offset..].chars().take(2).collect();
+
+            if pair == "//" {
+                self.skip_line_comment();
+            } else if pair == "/*" {
+                self.skip_block_comment(span);
+            } else if let Some(value) = self.str_to_type.get(pair.as_str()).cloned() {
+                self.next_char();
+                self.next_char();
+                self.push_token(value, span);
             } else if ch == '"' {
-                chars.next();
-                let mut identifier = String::new();
-
-                while let Some(&ch) = chars.peek() {
-                    if ch == '"' {
-                        break;
-                    } else {
-                        identifier.push(ch);
-                        chars.next();
-                    }
-                }
-                tokens.push(Type::DoubleQuotation(identifier));
-                chars.next();
+                let string = self.parse_string(span);
+                self.push_token(string, span);
             } else if let Some(value) = self.char_to_type.get(&ch).cloned() {
                 // `ch`が`char_to_type`のキーに存在する場合、`value`は`char_to_type[ch]`の値
-                tokens.push(value);
-                chars.next();
+                self.next_char();
+                self.push_token(value, span);
             } else {
                 match ch {
                     // tokenに数字をプッシュ
-                    '0'..='9' => tokens.push(self.parse_number(&mut chars).unwrap()),
-                    ' '
-                    | 'a'..='z'
-                    | 'A'..='Z'
-                    | '_'
-                    | '\u{3040}'..='\u{309F}'
-                    | '\u{4E00}'..='\u{9FFF}' => {
-                        if ch == ' ' {
-                            // tokenをスキップ
-                            chars.next();
-                        } else {
-                            // 単語ごとに区切られた文字列をTokensにプッシュする
-                            let words = self.parse_identifier(&mut chars).unwrap();
-                            tokens.push(words);
+                    '0'..='9' => {
+                        let number = self.parse_number(span);
+                        self.push_token(number, span);
+                    }
+                    ' ' | '\t' | '\r' | '\n' => {
+                        // tokenをスキップ
+                        self.next_char();
+                    }
+                    'a'..='z' | 'A'..='Z' | '_' => {
+                        // 単語ごとに区切られた文字列をTokensにプッシュする
+                        let words = self.parse_identifier().unwrap();
+                        self.push_token(words, span);
+                    }
+                    _ if self.is_japanese_char(ch) => {
+                        let words = self.parse_identifier().unwrap();
+                        self.push_token(words, span);
+                    }
+
+                    _ => {
+                        self.next_char();
+                        let mut span = span;
+                        span.end = self.offset;
+                        self.diagnostics.push(
+                            Diagnostic::error(diagnostic::INVALID_CHARACTER, vec![ch.to_string()])
+                                .with_label(span, "label.invalid_character", vec![]),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // `language:PYTHON <- { "..." }` の文字列は埋め込み先の言語のコードなので、そのまま読む
+    fn is_raw_block(&self) -> bool {
+        let types: Vec<&Type> = self
+            .tokens
+            .iter()
+            .rev()
+            .take(5)
+            .map(|token| &token.token_type)
+            .collect();
+        matches!(
+            types.as_slice(),
+            [
+                Type::LBraces,
+                Type::Equal,
+                Type::Identifier(_),
+                Type::Colon,
+                Type::Identifier(language)
+            ] if language == "language"
+        )
+    }
+
+    // 文字列リテラル。`{式}` を含む場合は Template になる
+    fn parse_string(&mut self, span: Span) -> Type {
+        self.next_char();
+        let raw = self.is_raw_block();
+        let mut parts = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            let Some(ch) = self.peek_char() else {
+                let mut quote = span;
+                quote.end = quote.start + 1;
+                self.diagnostics.push(
+                    Diagnostic::error(diagnostic::UNTERMINATED_STRING, vec![]).with_label(
+                        quote,
+                        "label.string_start",
+                        vec![],
+                    ),
+                );
+                break;
+            };
+            let mut ch_span = self.start_span();
+            self.next_char();
+
+            match ch {
+                '"' => break,
+                _ if raw => text.push(ch),
+                '\\' => {
+                    let escaped = match self.next_char() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(ch @ ('\\' | '"' | '\'' | '{' | '}')) => ch,
+                        Some(other) => {
+                            ch_span.end = self.offset;
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    diagnostic::UNKNOWN_ESCAPE,
+                                    vec![format!("\\{}", other)],
+                                )
+                                .with_label(
+                                    ch_span,
+                                    "label.unknown_escape",
+                                    vec![],
+                                ),
+                            );
+                            other
                         }
+                        None => continue,
+                    };
+                    text.push(escaped);
+                }
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
                     }
-                    '\t' => (),
+                    let code = self.lex_interpolation(ch_span);
+                    parts.push(TemplatePart::Code(code));
+                }
+                _ => text.push(ch),
+            }
+        }
 
-                    _ => panic!("Invalid character: {}", ch),
+        if parts.is_empty() {
+            return Type::DoubleQuotation(text);
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        Type::Template(parts)
+    }
+
+    // 文字列中の `{` から対応する `}` までをトークン列にする
+    // (埋め込み式の中に文字列は書けない。`"` が来たら文字列の終わりとみなす)
+    fn lex_interpolation(&mut self, mut open: Span) -> Vec<Token> {
+        let saved = std::mem::take(&mut self.tokens);
+        let mut depth = 0;
+
+        loop {
+            match self.peek_char() {
+                None | Some('"') => {
+                    open.end = open.start + 1;
+                    self.diagnostics.push(
+                        Diagnostic::error(diagnostic::UNCLOSED_INTERPOLATION, vec![]).with_label(
+                            open,
+                            "label.interpolation_start",
+                            vec![],
+                        ),
+                    );
+                    break;
                 }
+                Some('}') if depth == 0 => {
+                    self.next_char();
+                    break;
+                }
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                _ => {}
             }
+            self.lex_token();
         }
+        let span = self.start_span();
+        self.push_token(Type::EOF, span);
 
-        tokens
+        std::mem::replace(&mut self.tokens, saved)
     }
 
-    pub fn is_japanese_char(&mut self, c: char) -> bool {
+    pub fn is_japanese_char(&self, c: char) -> bool {
         let c = c as u32;
 
         (0x3040..=0x309F).contains(&c)   ||  // ひらがな
@@ -152,41 +505,224 @@ impl Lexer {
         (0x4E00..=0x9FFF).contains(&c) // 漢字
     }
 
-    fn parse_identifier(&mut self, chars: &mut Peekable<Chars>) -> Option<Type> {
+    fn parse_identifier(&mut self) -> Option<Type> {
         let mut identifier = String::new();
 
-        while let Some(&ch) = chars.peek() {
+        while let Some(ch) = self.peek_char() {
             if ch.is_ascii_alphanumeric() || self.is_japanese_char(ch) || ch == '_' {
                 identifier.push(ch);
-                chars.next();
+                self.next_char();
             } else {
                 break;
             }
         }
 
-        if identifier.is_empty() {
-            None
-        } else {
-            Some(Type::Identifier(identifier))
+        match identifier.as_str() {
+            "" => None,
+            "not" => Some(Type::Not),
+            "and" => Some(Type::And),
+            "or" => Some(Type::Or),
+            _ => Some(Type::Identifier(identifier)),
         }
     }
 
-    fn parse_number(&mut self, chars: &mut Peekable<Chars>) -> Option<Type> {
+    fn parse_number(&mut self, mut span: Span) -> Type {
         let mut number = String::new();
 
-        while let Some(&ch) = chars.peek() {
-            if ch.is_ascii_digit() || ch == '.' {
+        while let Some(ch) = self.peek_char() {
+            // `0..10` の `..` は小数点ではない
+            let is_decimal_point =
+                ch == '.' && self.code[self.offset + 1..].starts_with(|c: char| c.is_ascii_digit());
+            if ch.is_ascii_digit() || is_decimal_point {
                 number.push(ch);
-                chars.next();
+                self.next_char();
             } else {
                 break;
             }
         }
 
-        if number.is_empty() {
-            None
+        let parsed = if number.contains('.') {
+            number.parse().map(Type::Float).ok()
         } else {
-            Some(Type::Number(number.parse().unwrap()))
+            number.parse().map(Type::Number).ok()
+        };
+        match parsed {
+            Some(number) => number,
+            None => {
+                span.end = self.offset;
+                self.diagnostics.push(
+                    Diagnostic::error(diagnostic::INVALID_NUMBER, vec![number]).with_label(
+                        span,
+                        "label.invalid_number",
+                        vec![],
+                    ),
+                );
+                Type::Number(0)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(code: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+        let mut lexer = Lexer::new(code.to_string());
+        let tokens = lexer.lex();
+        (tokens, lexer.diagnostics)
+    }
+
+    fn types(code: &str) -> Vec<Type> {
+        let (tokens, diagnostics) = lex(code);
+        assert!(diagnostics.is_empty(), "unexpected errors in {:?}", code);
+        tokens.into_iter().map(|token| token.token_type).collect()
+    }
+
+    fn identifier(name: &str) -> Type {
+        Type::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn two_character_operators_win_over_one_character_ones() {
+        assert_eq!(
+            types("a <- b == c != d <= e >= f && g || h ..i"),
+            vec![
+                identifier("a"),
+                Type::Equal,
+                identifier("b"),
+                Type::EqualEqual,
+                identifier("c"),
+                Type::NotEqual,
+                identifier("d"),
+                Type::LessEqual,
+                identifier("e"),
+                Type::GreaterEqual,
+                identifier("f"),
+                Type::And,
+                identifier("g"),
+                Type::Or,
+                identifier("h"),
+                Type::DotDot,
+                identifier("i"),
+                Type::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_and_numbers() {
+        assert_eq!(
+            types("not x and 1.5 or 0..3"),
+            vec![
+                Type::Not,
+                identifier("x"),
+                Type::And,
+                Type::Float(1.5),
+                Type::Or,
+                Type::Number(0),
+                Type::DotDot,
+                Type::Number(3),
+                Type::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_skipped_and_doc_comments_kept() {
+        let (tokens, diagnostics) = lex("// note\n/* block\n */ /// first\n/// second\nint");
+        assert!(diagnostics.is_empty());
+        assert_eq!(tokens[0].token_type, identifier("int"));
+        assert_eq!(tokens[0].doc_comment.as_deref(), Some("first\nsecond"));
+    }
+
+    #[test]
+    fn escapes_in_strings() {
+        assert_eq!(
+            types(r#""a\n\t\"\\\{x\}\0""#),
+            vec![
+                Type::DoubleQuotation("a\n\t\"\\{x}\0".to_string()),
+                Type::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_escape_is_reported_at_the_escape() {
+        let (tokens, diagnostics) = lex(r#""ab\qc""#);
+        assert_eq!(
+            tokens[0].token_type,
+            Type::DoubleQuotation("abqc".to_string())
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, diagnostic::UNKNOWN_ESCAPE);
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(3, 5, 1, 4));
+    }
+
+    #[test]
+    fn raw_blocks_keep_backslashes() {
+        let (tokens, _) = lex(r#"language:PYTHON <- {"a\nb"}"#);
+        assert_eq!(
+            tokens[5].token_type,
+            Type::DoubleQuotation(r"a\nb".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolations_are_lexed_with_absolute_spans() {
+        let (tokens, diagnostics) = lex(r#"x <- "v={a + 1}!";"#);
+        assert!(diagnostics.is_empty());
+        let Type::Template(parts) = &tokens[2].token_type else {
+            panic!("expected a template, found {:?}", tokens[2].token_type);
+        };
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], TemplatePart::Text("v=".to_string()));
+        assert_eq!(parts[2], TemplatePart::Text("!".to_string()));
+        let TemplatePart::Code(code) = &parts[1] else {
+            panic!("expected code, found {:?}", parts[1]);
+        };
+        let code_types: Vec<&Type> = code.iter().map(|token| &token.token_type).collect();
+        assert_eq!(
+            code_types,
+            vec![&identifier("a"), &Type::Plus, &Type::Number(1), &Type::EOF]
+        );
+        assert_eq!(code[0].span, Span::new(9, 10, 1, 10));
+        assert_eq!(code[2].span, Span::new(13, 14, 1, 14));
+    }
+
+    #[test]
+    fn spans_track_lines_and_columns() {
+        let (tokens, _) = lex("int: x\n  <- 10;");
+        assert_eq!(tokens[3].token_type, Type::Equal);
+        assert_eq!(tokens[3].span, Span::new(9, 11, 2, 3));
+        assert_eq!(tokens[4].token_content, "10");
+        assert_eq!(tokens[4].span, Span::new(12, 14, 2, 6));
+    }
+
+    #[test]
+    fn japanese_identifiers() {
+        assert_eq!(
+            types("速さ <- 1"),
+            vec![identifier("速さ"), Type::Equal, Type::Number(1), Type::EOF]
+        );
+    }
+
+    #[test]
+    fn lexing_continues_after_errors() {
+        let (tokens, diagnostics) = lex("a $ b");
+        let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec![diagnostic::INVALID_CHARACTER]);
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(2, 3, 1, 3));
+        assert_eq!(tokens[1].token_type, identifier("b"));
+
+        let (_, diagnostics) = lex("\"open");
+        assert_eq!(diagnostics[0].code, diagnostic::UNTERMINATED_STRING);
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(0, 1, 1, 1));
+
+        let (_, diagnostics) = lex("a /* never closed");
+        assert_eq!(diagnostics[0].code, diagnostic::UNTERMINATED_COMMENT);
+
+        let (_, diagnostics) = lex("\"{a\"");
+        assert_eq!(diagnostics[0].code, diagnostic::UNCLOSED_INTERPOLATION);
+    }
+}
@@ -0,0 +1,760 @@
+use crate::diagnostic::{self, Diagnostic};
+use crate::module::Import;
+use crate::parse::{Node, NodeKind, CONSTRUCTOR_NAME};
+use crate::token::{Span, Type};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// 式の型
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    Str,
+    Bool,
+    Void,
+    List(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Class(String),
+    Enum(String),
+    // `language` ブロックや Python のモジュールから来たもので、型がわからない。どの型とも合う
+    Unknown,
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Float => write!(f, "float"),
+            Ty::Str => write!(f, "string"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Void => write!(f, "void"),
+            Ty::List(element) => write!(f, "list<{}>", element),
+            Ty::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Ty::Class(name) | Ty::Enum(name) => write!(f, "{}", name),
+            Ty::Unknown => write!(f, "_"),
+        }
+    }
+}
+
+impl Ty {
+    fn is_number(&self) -> bool {
+        matches!(self, Ty::Int | Ty::Float)
+    }
+}
+
+// 関数の引数 (名前、型、宣言の場所) と戻り値の型。import したものは場所が None
+#[derive(Clone)]
+struct FunctionType {
+    params: Vec<(String, Ty, Option<Span>)>,
+    returns: Ty,
+}
+
+#[derive(Clone, Default)]
+struct ClassType {
+    base: Option<String>,
+    fields: HashMap<String, Ty>,
+    methods: HashMap<String, FunctionType>,
+}
+
+// 式の場所と型。生成するときに printf の書式や Python の割り算を選ぶのに使う
+pub type Types = HashMap<Span, Ty>;
+
+struct Checker {
+    functions: HashMap<String, FunctionType>,
+    classes: HashMap<String, ClassType>,
+    enums: HashSet<String>,
+    // 別名で import したクラスと列挙型 (このファイルでの名前と、モジュールでの名前)
+    renamed: HashMap<String, String>,
+    // `language` ブロックや .tf 以外のモジュールから来た名前。型がわからないが、型の名前に使ってもよい
+    foreign: HashSet<String>,
+    // 外側から順に、変数の名前と型
+    scopes: Vec<HashMap<String, Ty>>,
+    // 検査中の関数の戻り値の型と、関数の場所
+    returns: (Ty, Span),
+    types: Types,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    // `int` / `list<int>` / `map<string, int>` / クラス名 / 列挙型名
+    fn ty(&self, name: &str) -> Ty {
+        if let Some(element) = name.strip_prefix("list<").and_then(|n| n.strip_suffix('>')) {
+            return Ty::List(Box::new(self.ty(element)));
+        }
+        if let Some((key, value)) = name
+            .strip_prefix("map<")
+            .and_then(|n| n.strip_suffix('>'))
+            .and_then(|n| n.split_once(", "))
+        {
+            return Ty::Map(Box::new(self.ty(key)), Box::new(self.ty(value)));
+        }
+        match name {
+            "int" => Ty::Int,
+            "float" => Ty::Float,
+            "string" => Ty::Str,
+            "bool" => Ty::Bool,
+            "void" => Ty::Void,
+            _ => {
+                let name = self.renamed.get(name).map_or(name, String::as_str);
+                if self.classes.contains_key(name) {
+                    Ty::Class(name.to_string())
+                } else if self.enums.contains(name) {
+                    Ty::Enum(name.to_string())
+                } else {
+                    Ty::Unknown
+                }
+            }
+        }
+    }
+
+    // 型の名前の中で、見つからない名前
+    fn unknown_type_name<'n>(&self, name: &'n str) -> Option<&'n str> {
+        if let Some(element) = name.strip_prefix("list<").and_then(|n| n.strip_suffix('>')) {
+            return self.unknown_type_name(element);
+        }
+        if let Some((key, value)) = name
+            .strip_prefix("map<")
+            .and_then(|n| n.strip_suffix('>'))
+            .and_then(|n| n.split_once(", "))
+        {
+            return self
+                .unknown_type_name(key)
+                .or_else(|| self.unknown_type_name(value));
+        }
+        let found = self.ty(name) != Ty::Unknown || self.foreign.contains(name);
+        (!found).then_some(name)
+    }
+
+    // 宣言に書いた型の名前が見つからなければ報告する
+    fn check_type_name(&mut self, name: &str, span: Span) {
+        if let Some(unknown) = self.unknown_type_name(name) {
+            self.diagnostics.push(
+                Diagnostic::error(diagnostic::UNKNOWN_TYPE, vec![unknown.to_string()]).with_label(
+                    span,
+                    "label.undefined",
+                    vec![],
+                ),
+            );
+        }
+    }
+
+    fn function_type(&self, node: &Node, imported: bool) -> Option<FunctionType> {
+        let Some(NodeKind::Function {
+            params,
+            function_type: Type::Identifier(returns),
+            ..
+        }) = &node.kind
+        else {
+            return None;
+        };
+        let params = params
+            .iter()
+            .filter_map(|param| match &param.kind {
+                Some(NodeKind::Param { name, ty, .. }) => {
+                    let ty = ty.as_deref().map_or(Ty::Unknown, |ty| self.ty(ty));
+                    Some((name.clone(), ty, (!imported).then_some(param.span)))
+                }
+                _ => None,
+            })
+            .collect();
+        Some(FunctionType {
+            params,
+            returns: self.ty(returns),
+        })
+    }
+
+    // クラスと列挙型の名前を先に集める (型の名前を読むのに使う)
+    fn collect_name(&mut self, node: &Node) {
+        match &node.kind {
+            Some(NodeKind::Class { class_name, .. }) => {
+                self.classes
+                    .insert(class_name.clone(), ClassType::default());
+            }
+            Some(NodeKind::Enum { name, .. }) => {
+                self.enums.insert(name.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_definition(&mut self, node: &Node, imported: bool) {
+        match &node.kind {
+            Some(NodeKind::Function {
+                function_name: Type::Identifier(name),
+                ..
+            }) => {
+                if let Some(function) = self.function_type(node, imported) {
+                    self.functions.insert(name.clone(), function);
+                }
+            }
+            Some(NodeKind::Class {
+                class_name,
+                base,
+                fields,
+                menbers,
+                ..
+            }) => {
+                // 自分自身にたどり着く基底クラスは循環になるので捨てる (check が報告する)
+                let base = base
+                    .as_ref()
+                    .map(|(base, _)| {
+                        self.renamed
+                            .get(base)
+                            .cloned()
+                            .unwrap_or_else(|| base.clone())
+                    })
+                    .filter(|base| !self.is_subclass(base, class_name));
+                let fields = fields
+                    .iter()
+                    .filter_map(|field| match &field.kind {
+                        Some(NodeKind::Let { v_name, v_type, .. }) => {
+                            Some((v_name.clone(), self.ty(v_type)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let methods = menbers
+                    .iter()
+                    .filter_map(|menber| match &menber.kind {
+                        Some(NodeKind::Function {
+                            function_name: Type::Identifier(name),
+                            ..
+                        }) => Some((name.clone(), self.function_type(menber, imported)?)),
+                        _ => None,
+                    })
+                    .collect();
+                self.classes.insert(
+                    class_name.clone(),
+                    ClassType {
+                        base,
+                        fields,
+                        methods,
+                    },
+                );
+            }
+            Some(NodeKind::Global { declaration, .. }) => {
+                if let Some(NodeKind::Let { v_name, v_type, .. }) = &declaration.kind {
+                    let ty = self.ty(v_type);
+                    self.scopes[0].insert(v_name.clone(), ty);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 基底クラスをたどってフィールドやメソッドを探す
+    fn find_in_class<T>(
+        &self,
+        class_name: &str,
+        find: impl Fn(&ClassType) -> Option<&T>,
+    ) -> Option<&T> {
+        let mut class_name = Some(class_name);
+        while let Some(class) = class_name.and_then(|name| self.classes.get(name)) {
+            if let Some(found) = find(class) {
+                return Some(found);
+            }
+            class_name = class.base.as_deref();
+        }
+        None
+    }
+
+    fn is_subclass(&self, class_name: &str, base: &str) -> bool {
+        let mut class_name = Some(class_name);
+        while let Some(name) = class_name {
+            if name == base {
+                return true;
+            }
+            class_name = self
+                .classes
+                .get(name)
+                .and_then(|class| class.base.as_deref());
+        }
+        false
+    }
+
+    // found 型の値を expected 型の場所に入れられるか (int は float に、派生クラスは基底クラスになれる)
+    fn assignable(&self, expected: &Ty, found: &Ty) -> bool {
+        match (expected, found) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Float, Ty::Int) => true,
+            (Ty::List(expected), Ty::List(found)) => self.assignable(expected, found),
+            (Ty::Map(expected_key, expected_value), Ty::Map(found_key, found_value)) => {
+                self.assignable(expected_key, found_key)
+                    && self.assignable(expected_value, found_value)
+            }
+            (Ty::Class(base), Ty::Class(class_name)) => self.is_subclass(class_name, base),
+            _ => expected == found,
+        }
+    }
+
+    // 型が合わなければ報告する。declared は型を宣言した場所と、そこに付けるラベル
+    fn expect(
+        &mut self,
+        expected: &Ty,
+        found: &Ty,
+        span: Span,
+        declared: Option<(Span, &'static str)>,
+    ) {
+        if self.assignable(expected, found) {
+            return;
+        }
+        let mut diagnostic = Diagnostic::error(
+            diagnostic::TYPE_MISMATCH,
+            vec![expected.to_string(), found.to_string()],
+        )
+        .with_label(span, "label.found_type", vec![found.to_string()]);
+        if let Some((declared, label)) = declared {
+            diagnostic =
+                diagnostic.with_secondary_label(declared, label, vec![expected.to_string()]);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    // 2つの値をまとめた型 (list や map のリテラルの要素の型)
+    fn join(&self, lhs: Ty, rhs: &Ty) -> Option<Ty> {
+        match (&lhs, rhs) {
+            (Ty::Unknown, _) => Some(rhs.clone()),
+            (_, Ty::Unknown) => Some(lhs),
+            _ if self.assignable(&lhs, rhs) => Some(lhs),
+            _ if self.assignable(rhs, &lhs) => Some(rhs.clone()),
+            _ => None,
+        }
+    }
+
+    fn elements(&mut self, nodes: &[&Node]) -> Ty {
+        let mut element = Ty::Unknown;
+        for node in nodes {
+            let found = self.expression(node);
+            match self.join(element.clone(), &found) {
+                Some(joined) => element = joined,
+                None => self.expect(&element, &found, node.span, None),
+            }
+        }
+        element
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Ty> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn declare(&mut self, name: &str, ty: Ty) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    // 引数の型が関数の定義と合っているか (数が合っているかは check で調べてある)
+    fn arguments(&mut self, function: &FunctionType, args: &[Node], kwargs: &[(String, Node)]) {
+        let given = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| (function.params.get(i), arg))
+            .chain(kwargs.iter().map(|(name, value)| {
+                let param = function.params.iter().find(|(param, _, _)| param == name);
+                (param, value)
+            }));
+        for (param, arg) in given {
+            let found = self.expression(arg);
+            if let Some((_, expected, span)) = param {
+                let declared = span.map(|span| (span, "label.expected_type"));
+                self.expect(expected, &found, arg.span, declared);
+            }
+        }
+    }
+
+    fn function(&mut self, node: &Node, class_name: Option<&str>) {
+        let Some(NodeKind::Function {
+            params,
+            body,
+            function_type,
+            ..
+        }) = &node.kind
+        else {
+            return;
+        };
+        if let Type::Identifier(returns) = function_type {
+            self.check_type_name(returns, node.span);
+        }
+        for param in params {
+            if let Some(NodeKind::Param { ty: Some(ty), .. }) = &param.kind {
+                self.check_type_name(ty, param.span);
+            }
+        }
+        let Some(function) = self.function_type(node, false) else {
+            return;
+        };
+        self.scopes.push(HashMap::new());
+        if let Some(class_name) = class_name {
+            self.declare("self", Ty::Class(class_name.to_string()));
+            if let Some(base) = self
+                .classes
+                .get(class_name)
+                .and_then(|class| class.base.clone())
+            {
+                self.declare("super", Ty::Class(base));
+            }
+        }
+        for (param, (name, ty, span)) in params.iter().zip(function.params) {
+            if let Some(NodeKind::Param {
+                default: Some(default),
+                ..
+            }) = &param.kind
+            {
+                let found = self.expression(default);
+                let declared = span.map(|span| (span, "label.expected_type"));
+                self.expect(&ty, &found, default.span, declared);
+            }
+            self.declare(&name, ty);
+        }
+        self.returns = (function.returns, node.span);
+        self.expression(body);
+        self.scopes.pop();
+    }
+
+    // 式の型を求めて記録する。文 (void) とコンパイラが作ったノード (場所がない) は記録しない
+    fn expression(&mut self, node: &Node) -> Ty {
+        let ty = self.expression_type(node);
+        if ty != Ty::Void && node.span != Span::default() {
+            self.types.insert(node.span, ty.clone());
+        }
+        ty
+    }
+
+    // 式の型を求めながら、中の文と式を検査する。文の型は void
+    fn expression_type(&mut self, node: &Node) -> Ty {
+        match &node.kind {
+            Some(NodeKind::Num(_)) => Ty::Int,
+            Some(NodeKind::Float(_)) => Ty::Float,
+            Some(NodeKind::Bool(_)) => Ty::Bool,
+            Some(NodeKind::StrLiteral(_)) => Ty::Str,
+            Some(NodeKind::FormatStr(parts)) => {
+                for part in parts {
+                    self.expression(part);
+                }
+                Ty::Str
+            }
+            Some(NodeKind::Str(name)) => self.lookup(name).cloned().unwrap_or(Ty::Unknown),
+            Some(NodeKind::BinaryOp { op, lhs, rhs }) => {
+                let lhs_ty = self.expression(lhs);
+                let rhs_ty = self.expression(rhs);
+                match (op, &lhs_ty, &rhs_ty) {
+                    (_, Ty::Unknown, _) | (_, _, Ty::Unknown) => Ty::Unknown,
+                    (_, Ty::Int, Ty::Int) => Ty::Int,
+                    _ if lhs_ty.is_number() && rhs_ty.is_number() => Ty::Float,
+                    (Type::Plus, Ty::Str, Ty::Str) => Ty::Str,
+                    _ => {
+                        self.invalid_operands(op, &lhs_ty, &rhs_ty, node.span);
+                        Ty::Unknown
+                    }
+                }
+            }
+            Some(NodeKind::Compare { lhs, op, rhs }) => {
+                let lhs_ty = self.expression(lhs);
+                let rhs_ty = self.expression(rhs);
+                // 大小を比べられるのは数だけ
+                let comparable = match (&**op, &lhs_ty, &rhs_ty) {
+                    (_, Ty::Unknown, _) | (_, _, Ty::Unknown) => true,
+                    (Type::EqualEqual | Type::NotEqual, _, _) => {
+                        self.assignable(&lhs_ty, &rhs_ty) || self.assignable(&rhs_ty, &lhs_ty)
+                    }
+                    _ => lhs_ty.is_number() && rhs_ty.is_number(),
+                };
+                if !comparable {
+                    self.invalid_operands(op, &lhs_ty, &rhs_ty, node.span);
+                }
+                Ty::Bool
+            }
+            Some(NodeKind::Logical { lhs, rhs, .. }) => {
+                self.expression(lhs);
+                self.expression(rhs);
+                Ty::Bool
+            }
+            Some(NodeKind::UnaryOp { op, operand }) => {
+                let operand = self.expression(operand);
+                match op {
+                    Type::Not => Ty::Bool,
+                    _ => operand,
+                }
+            }
+            Some(NodeKind::List(items)) => {
+                let items: Vec<&Node> = items.iter().collect();
+                Ty::List(Box::new(self.elements(&items)))
+            }
+            Some(NodeKind::Map(entries)) => {
+                let keys: Vec<&Node> = entries.iter().map(|(key, _)| key).collect();
+                let values: Vec<&Node> = entries.iter().map(|(_, value)| value).collect();
+                let key = self.elements(&keys);
+                let value = self.elements(&values);
+                Ty::Map(Box::new(key), Box::new(value))
+            }
+            Some(NodeKind::Index { target, index }) => {
+                let target = self.expression(target);
+                let found = self.expression(index);
+                match target {
+                    Ty::List(element) => {
+                        self.expect(&Ty::Int, &found, index.span, None);
+                        *element
+                    }
+                    Ty::Map(key, value) => {
+                        self.expect(&key, &found, index.span, None);
+                        *value
+                    }
+                    _ => Ty::Unknown,
+                }
+            }
+            Some(NodeKind::Field { target, name }) => {
+                // State.Idle
+                if let Some(NodeKind::Str(enum_name)) = &target.kind {
+                    let enum_name = self.renamed.get(enum_name).unwrap_or(enum_name);
+                    if self.lookup(enum_name).is_none() && self.enums.contains(enum_name) {
+                        return Ty::Enum(enum_name.clone());
+                    }
+                }
+                match self.expression(target) {
+                    Ty::Class(class_name) => self
+                        .find_in_class(&class_name, |class| class.fields.get(name))
+                        .cloned()
+                        .unwrap_or(Ty::Unknown),
+                    _ => Ty::Unknown,
+                }
+            }
+            Some(NodeKind::MethodCall {
+                target,
+                name,
+                args,
+                kwargs,
+            }) => {
+                let method = match self.expression(target) {
+                    Ty::Class(class_name) => self
+                        .find_in_class(&class_name, |class| class.methods.get(name))
+                        .cloned(),
+                    _ => None,
+                };
+                self.call(method, args, kwargs)
+            }
+            Some(NodeKind::Call {
+                function_name,
+                args,
+                kwargs,
+            }) => {
+                let class_name = self.renamed.get(function_name).unwrap_or(function_name);
+                if self.classes.contains_key(class_name) {
+                    // `Main(1)` はコンストラクタの呼び出し
+                    let class_name = class_name.clone();
+                    let constructor = self
+                        .find_in_class(&class_name, |class| class.methods.get(CONSTRUCTOR_NAME))
+                        .cloned();
+                    self.call(constructor, args, kwargs);
+                    Ty::Class(class_name)
+                } else {
+                    let function = self.functions.get(function_name).cloned();
+                    self.call(function, args, kwargs)
+                }
+            }
+            Some(NodeKind::Let {
+                v_name,
+                v_type,
+                v_formula,
+                this_is_define,
+            }) => {
+                self.check_type_name(v_type, node.span);
+                let declared = self.ty(v_type);
+                if *this_is_define {
+                    let found = self.expression(v_formula);
+                    self.expect(&declared, &found, v_formula.span, None);
+                }
+                self.declare(v_name, declared);
+                Ty::Void
+            }
+            Some(NodeKind::Assign { lhs, rhs }) => {
+                let expected = self.expression(lhs);
+                let found = self.expression(rhs);
+                self.expect(&expected, &found, rhs.span, None);
+                Ty::Void
+            }
+            Some(NodeKind::Return(value)) => {
+                let found = self.expression(value);
+                let (expected, function) = self.returns.clone();
+                self.expect(
+                    &expected,
+                    &found,
+                    value.span,
+                    Some((function, "label.return_type")),
+                );
+                Ty::Void
+            }
+            Some(NodeKind::Block(statements)) => {
+                self.scopes.push(HashMap::new());
+                for statement in statements {
+                    self.expression(statement);
+                }
+                self.scopes.pop();
+                Ty::Void
+            }
+            Some(NodeKind::For {
+                var, iter, body, ..
+            }) => {
+                let element = match &iter.kind {
+                    Some(NodeKind::Range { start, end }) => {
+                        for bound in [start, end] {
+                            let found = self.expression(bound);
+                            self.expect(&Ty::Int, &found, bound.span, None);
+                        }
+                        Ty::Int
+                    }
+                    _ => match self.expression(iter) {
+                        Ty::List(element) => *element,
+                        Ty::Map(key, _) => *key,
+                        Ty::Str => Ty::Str,
+                        _ => Ty::Unknown,
+                    },
+                };
+                self.scopes.push(HashMap::new());
+                self.declare(var, element);
+                self.expression(body);
+                self.scopes.pop();
+                Ty::Void
+            }
+            _ => {
+                for child in node.children() {
+                    self.expression(child);
+                }
+                Ty::Void
+            }
+        }
+    }
+
+    // 関数やメソッドの呼び出し。定義がわからなければ引数だけ検査する
+    fn call(
+        &mut self,
+        function: Option<FunctionType>,
+        args: &[Node],
+        kwargs: &[(String, Node)],
+    ) -> Ty {
+        match function {
+            Some(function) => {
+                self.arguments(&function, args, kwargs);
+                function.returns
+            }
+            None => {
+                for arg in args.iter().chain(kwargs.iter().map(|(_, value)| value)) {
+                    self.expression(arg);
+                }
+                Ty::Unknown
+            }
+        }
+    }
+
+    fn invalid_operands(&mut self, op: &Type, lhs: &Ty, rhs: &Ty, span: Span) {
+        self.diagnostics.push(
+            Diagnostic::error(
+                diagnostic::INVALID_OPERANDS,
+                vec![op.to_string(), lhs.to_string(), rhs.to_string()],
+            )
+            .with_label(span, "label.invalid_operands", vec![]),
+        );
+    }
+}
+
+// 型の注釈をもとに、宣言・代入・return・呼び出しの引数・演算の型を検査する。
+// import したファイルの関数やクラスの型も使う。imports は .tf ファイルを読み込んだ import 文
+pub fn type_check(root: &Node, imported: &[&Node], imports: &[Import]) -> (Vec<Diagnostic>, Types) {
+    let mut checker = Checker {
+        functions: HashMap::new(),
+        classes: HashMap::new(),
+        enums: HashSet::new(),
+        renamed: HashMap::new(),
+        foreign: HashSet::new(),
+        scopes: vec![HashMap::new()],
+        returns: (Ty::Unknown, Span::default()),
+        types: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    let imported: Vec<&Node> = imported
+        .iter()
+        .flat_map(|root| definitions_of(root))
+        .collect();
+    let definitions = definitions_of(root);
+    let aliases = imports
+        .iter()
+        .flat_map(|import| &import.names)
+        .filter(|(name, local)| name != local);
+
+    for node in definitions {
+        match &node.kind {
+            Some(NodeKind::RawLanguage { raw_data, .. }) => {
+                let words = raw_data
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .filter(|word| !word.is_empty());
+                checker.foreign.extend(words.map(str::to_string));
+            }
+            Some(NodeKind::Import {
+                path,
+                names: Some(names),
+                ..
+            }) if !imports.iter().any(|import| import.path == path.join(".")) => {
+                for (name, local, _) in names {
+                    checker
+                        .foreign
+                        .insert(local.clone().unwrap_or_else(|| name.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for node in imported.iter().copied().chain(definitions) {
+        checker.collect_name(node);
+    }
+    for (name, local) in aliases.clone() {
+        if checker.classes.contains_key(name) || checker.enums.contains(name) {
+            checker.renamed.insert(local.clone(), name.clone());
+        }
+    }
+    for node in &imported {
+        checker.collect_definition(node, true);
+    }
+    for (name, local) in aliases {
+        if let Some(function) = checker.functions.get(name).cloned() {
+            checker.functions.insert(local.clone(), function);
+        }
+    }
+    for node in definitions {
+        checker.collect_definition(node, false);
+    }
+
+    for node in definitions {
+        match &node.kind {
+            Some(NodeKind::Function { .. }) => checker.function(node, None),
+            Some(NodeKind::Class {
+                class_name,
+                fields,
+                menbers,
+                ..
+            }) => {
+                // フィールドはメソッドの中では `self.` を付けて使うので、変数としては残さない
+                checker.scopes.push(HashMap::new());
+                for field in fields {
+                    checker.expression(field);
+                }
+                checker.scopes.pop();
+                for menber in menbers {
+                    checker.function(menber, Some(class_name));
+                }
+            }
+            Some(NodeKind::Global { declaration, .. }) => {
+                checker.expression(declaration);
+            }
+            _ => {}
+        }
+    }
+    (checker.diagnostics, checker.types)
+}
+
+fn definitions_of(root: &Node) -> &[Node] {
+    match &root.kind {
+        Some(NodeKind::Root { function_define_s }) => function_define_s,
+        _ => &[],
+    }
+}
@@ -0,0 +1,199 @@
+// tests/golden/<名前>.tf を Python と C に変換して実行し、標準出力を <名前>.<出力先>.out と比べる。
+// <名前>.<出力先>.err があれば、変換がエラーになり、標準エラー出力がその内容になることを確かめる
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const COMPILER: &str = env!("CARGO_BIN_EXE_tuningfork");
+const TARGETS: [&str; 2] = ["python", "c"];
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+// 出力先ごとの作業ディレクトリ (変換したファイルはここに書き出される)
+fn work_dir(name: &str, target: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "tuningfork-{}-{}-{}",
+        name,
+        target,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create a work directory");
+    dir
+}
+
+// python3 や gcc がなければ None にして、そのテストは飛ばす
+fn run(command: &mut Command) -> Option<Output> {
+    match command.output() {
+        Ok(output) => Some(output),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => panic!("failed to run {:?}: {}", command, error),
+    }
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// 変換したプログラムを実行する。C はコンパイルしてから実行する
+fn execute(name: &str, target: &str, dir: &Path, compiled: &Output) -> Option<Output> {
+    if target == "python" {
+        return run(Command::new("python3")
+            .arg(format!("{}.py", name))
+            .current_dir(dir));
+    }
+    fs::write(dir.join(format!("{}.c", name)), &compiled.stdout).expect("failed to write C");
+    let built = run(Command::new("gcc")
+        .args(["-std=c99", "-o", name])
+        .arg(format!("{}.c", name))
+        .current_dir(dir))?;
+    assert!(
+        built.status.success(),
+        "{} (c): gcc failed:\n{}",
+        name,
+        text(&built.stderr)
+    );
+    run(&mut Command::new(dir.join(name)))
+}
+
+fn golden(name: &str) {
+    let source = golden_dir().join(format!("{}.tf", name));
+    for target in TARGETS {
+        let expected = |extension| {
+            fs::read_to_string(golden_dir().join(format!("{}.{}.{}", name, target, extension))).ok()
+        };
+        let dir = work_dir(name, target);
+        fs::copy(&source, dir.join(format!("{}.tf", name))).expect("failed to copy the source");
+        let compiled = Command::new(COMPILER)
+            .arg(format!("{}.tf", name))
+            .args(["--target", target, "--lang", "en"])
+            .current_dir(&dir)
+            .output()
+            .expect("failed to run the compiler");
+
+        if let Some(errors) = expected("err") {
+            assert!(
+                !compiled.status.success(),
+                "{} ({}): expected the conversion to fail",
+                name,
+                target
+            );
+            assert_eq!(text(&compiled.stderr), errors, "{} ({})", name, target);
+        } else {
+            assert!(
+                compiled.status.success(),
+                "{} ({}): conversion failed:\n{}",
+                name,
+                target,
+                text(&compiled.stderr)
+            );
+            let Some(output) = execute(name, target, &dir, &compiled) else {
+                eprintln!(
+                    "skipping {} ({}): python3 or gcc is not installed",
+                    name, target
+                );
+                continue;
+            };
+            assert!(
+                output.status.success(),
+                "{} ({}): the program failed:\n{}",
+                name,
+                target,
+                text(&output.stderr)
+            );
+            let out = expected("out").unwrap_or_else(|| panic!("missing {}.{}.out", name, target));
+            assert_eq!(text(&output.stdout), out, "{} ({})", name, target);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[test]
+fn types() {
+    golden("types");
+}
+
+#[test]
+fn format() {
+    golden("format");
+}
+
+#[test]
+fn classes() {
+    golden("classes");
+}
+
+#[test]
+fn maps() {
+    golden("maps");
+}
+
+#[test]
+fn upcast_copy() {
+    golden("upcast_copy");
+}
+
+#[test]
+fn arguments() {
+    golden("arguments");
+}
+
+#[test]
+fn types_error() {
+    golden("types_error");
+}
+
+#[test]
+fn inheritance_cycle() {
+    golden("inheritance_cycle");
+}
+
+#[test]
+fn list_arguments() {
+    golden("list_arguments");
+}
+
+#[test]
+fn class_fields() {
+    golden("class_fields");
+}
+
+#[test]
+fn method_receivers() {
+    golden("method_receivers");
+}
+
+#[test]
+fn map_capacity() {
+    golden("map_capacity");
+}
+
+#[test]
+fn shadowing() {
+    golden("shadowing");
+}
+
+#[test]
+fn elif() {
+    golden("elif");
+}
+
+#[test]
+fn loops() {
+    golden("loops");
+}
+
+#[test]
+fn loops_error() {
+    golden("loops_error");
+}
+
+#[test]
+fn enums() {
+    golden("enums");
+}
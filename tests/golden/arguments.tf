@@ -0,0 +1,14 @@
+class Base <- {
+    int: n <- 0;
+
+    int: go(self, int: by <- 1) <- {
+        return self.n + by;
+    }
+}
+
+int: main <- {
+    Base: b <- Base();
+    print(b.go(1, 2));
+    print(b.go(step = 3));
+    return 0;
+}
@@ -0,0 +1,34 @@
+// 初期値のないフィールドと、代入で作り直すクラスの値
+class Wheel <- {
+    int: size <- 1;
+    string: label;
+    int: spare;
+
+    int: grow(self, int: n) <- {
+        self.size <- self.size + n;
+        return self.size;
+    }
+}
+
+class Car <- {
+    Wheel: left <- Wheel();
+    Wheel: right;
+    string: name;
+
+    void: _init_(self) <- {
+        self.right <- Wheel();
+        self.right.size <- 3;
+    }
+}
+
+int: main <- {
+    Car: car <- Car();
+    Wheel: w <- Wheel();
+    print("[{w.label}] {w.spare} {w.size}");
+    print(car.left.size + car.right.size);
+    print("[{car.name}]");
+    w.size <- 5;
+    w <- Wheel();
+    print(w.size);
+    return 0;
+}
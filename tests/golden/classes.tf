@@ -0,0 +1,61 @@
+class Base <- {
+    int: power <- 1;
+
+    int: speed(self) <- {
+        return self.power;
+    }
+
+    int: boost(self, int: by <- 1) <- {
+        return self.power + by;
+    }
+}
+
+class Fast : Base <- {
+    int: speed(self) <- {
+        return self.power * 10;
+    }
+}
+
+class Faster : Fast <- {
+    int: speed(self) <- {
+        return self.power * 100;
+    }
+}
+
+int: count <- 3;
+int: total <- 5;
+
+int: show(Base: b) <- {
+    return b.speed();
+}
+
+int: twice(int: n) <- {
+    return n * 2;
+}
+
+int: locals <- {
+    Fast: count <- Fast();
+    list<int>: total <- [1, 2];
+    return count.speed() + total[0];
+}
+
+int: globals <- {
+    return twice(count) + twice(total);
+}
+
+int: main <- {
+    Fast: f <- Fast();
+    Faster: ff <- Faster();
+    Base: b <- Base();
+    Base: g <- f;
+    print(g.speed());
+    g.power <- 2;
+    print(f.speed());
+    g <- ff;
+    print(show(g));
+    g <- b;
+    print(g.boost(by = 3));
+    print(locals());
+    print(globals());
+    return 0;
+}
@@ -0,0 +1,35 @@
+// if / elif / else の連鎖と、その中の入れ子のブロック
+string: grade(int: score) <- {
+    if score >= 80 <- {
+        return "A";
+    } elif score >= 60 <- {
+        return "B";
+    } elif score >= 40 <- {
+        if score / 2 * 2 == score <- {
+            return "C-even";
+        } elif score > 45 <- {
+            return "C-high";
+        } else <- {
+            return "C";
+        }
+    } else <- {
+        return "D";
+    }
+}
+
+int: main <- {
+    print(grade(90));
+    print(grade(70));
+    print(grade(42));
+    print(grade(47));
+    print(grade(41));
+    print(grade(10));
+    int: n <- 3;
+    if n == 1 <- {
+        print("one");
+    } elif n == 3 <- {
+        print("three");
+    }
+    print("done");
+    return 0;
+}
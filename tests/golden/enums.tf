@@ -0,0 +1,50 @@
+// 列挙型と match (値が複数のアームと else)
+enum State <- { Idle, Driving, Turning, Stopped }
+
+string: describe(State: state) <- {
+    match state <- {
+        State.Idle <- {
+            return "idle";
+        }
+        State.Driving, State.Turning <- {
+            return "moving";
+        }
+        State.Stopped <- {
+            return "stopped";
+        }
+    }
+    return "?";
+}
+
+State: next(State: state) <- {
+    match state <- {
+        State.Idle <- {
+            return State.Driving;
+        }
+        State.Driving <- {
+            return State.Turning;
+        }
+        else <- {
+            return State.Stopped;
+        }
+    }
+    return state;
+}
+
+int: main <- {
+    State: state <- State.Idle;
+    while state != State.Stopped <- {
+        print(describe(state));
+        state <- next(state);
+    }
+    print(describe(state));
+    match next(State.Idle) <- {
+        State.Driving <- {
+            print("driving next");
+        }
+        else <- {
+            print("other");
+        }
+    }
+    return 0;
+}
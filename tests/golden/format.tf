@@ -0,0 +1,36 @@
+class Point <- {
+    float: x <- 1.5;
+    string: name <- "p";
+}
+
+float: half(int: n) <- {
+    return n / 2.0;
+}
+
+int: twice(n) <- {
+    return n * 2;
+}
+
+// 書式文字列を引数に渡す (C では呼び出しの後で解放する)
+int: shout(string: text) <- {
+    print("{text}!");
+    return 0;
+}
+
+int: main <- {
+    Point: p <- Point();
+    list<float>: xs <- [0.25, 0.5];
+    string: a <- "a={1}";
+    string: b <- "b={2}";
+    string: c <- "c={3}";
+    string: d <- "d={4}";
+    string: e <- "e={5}";
+    string: joined <- "{a}{b}{c}{d}{e}012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789012345678901234567890123456789-end";
+    print(a);
+    print(joined);
+    print("{p.name}: {p.x} {xs[1]} {half(3)} {twice(4)} 100%");
+    for i in 0..2 <- {
+        shout("i={i}");
+    }
+    return 0;
+}
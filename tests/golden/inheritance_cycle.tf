@@ -0,0 +1,16 @@
+// 循環する継承はエラーになり、変換が止まらなくならない
+class A : B <- {
+    int: x <- 1;
+}
+class B : A <- {
+    int: y <- 2;
+}
+class C : C <- {
+    int: z <- 3;
+}
+int: main() <- {
+    A: a <- A();
+    C: c <- C();
+    print(a.x + c.z);
+    return 0;
+}
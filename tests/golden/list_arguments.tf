@@ -0,0 +1,24 @@
+// 引数に書いたリスト (C では複合リテラルと長さにする)
+int: sum(list<int>: xs) <- {
+    int: total <- 0;
+    for x in xs <- {
+        total <- total + x;
+    }
+    return total;
+}
+
+class Counter <- {
+    int: base <- 10;
+    int: add(self, list<int>: xs) <- {
+        return self.base + sum(xs);
+    }
+}
+
+int: main <- {
+    Counter: counter <- Counter();
+    print(sum([4, 5]));
+    print(sum([]));
+    print(sum(xs = [1, 2, 3]));
+    print(counter.add([2 * 2, 1]));
+    return 0;
+}
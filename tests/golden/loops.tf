@@ -0,0 +1,36 @@
+// ラベル付きの break / continue と、while の中の for
+int: main <- {
+    @outer for i in 0..4 <- {
+        for j in 0..4 <- {
+            if j == 2 <- {
+                continue @outer;
+            }
+            if i == 3 <- {
+                break @outer;
+            }
+            print("{i} {j}");
+        }
+    }
+    int: n <- 0;
+    @scan while true <- {
+        n <- n + 1;
+        for k in 0..3 <- {
+            if k == n and n == 1 <- {
+                continue @scan;
+            }
+            if n > 2 <- {
+                break @scan;
+            }
+        }
+        print("n={n}");
+    }
+    while n < 6 <- {
+        n <- n + 1;
+        if n == 4 <- {
+            continue;
+        }
+        print(n);
+    }
+    print("done");
+    return 0;
+}
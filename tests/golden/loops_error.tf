@@ -0,0 +1,8 @@
+// ループの外の break と、宣言していないラベル
+int: main <- {
+    break;
+    for i in 0..2 <- {
+        continue @missing;
+    }
+    return 0;
+}
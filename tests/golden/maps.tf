@@ -0,0 +1,25 @@
+map<string, int>: table <- {"a": 1};
+map<int, int>: squares;
+
+class Inventory <- {
+    map<string, int>: items <- {"bolt": 4};
+    map<string, int>: spare;
+
+    int: restock(self, int: count) <- {
+        self.items["nut"] <- count;
+        self.spare <- {"bolt": count * 2};
+        return self.items["nut"] + self.spare["bolt"];
+    }
+}
+
+int: main <- {
+    Inventory: inventory <- Inventory();
+    print(table["a"] + inventory.items["bolt"]);
+    print(inventory.restock(3));
+    inventory.items["bolt"] <- 10;
+    print(inventory.items["bolt"] + inventory.items["nut"]);
+    squares[3] <- 9;
+    table <- {"b": 2, "c": 3};
+    print(squares[3] + table["b"] + table["c"]);
+    return 0;
+}
@@ -0,0 +1,45 @@
+// 変数でない値のメソッドを呼ぶ (フィールド、リストの要素、戻り値)
+class Part <- {
+    int: power <- 1;
+    int: speed(self) <- {
+        return self.power;
+    }
+}
+class Wheel : Part <- {
+    int: size <- 2;
+    int: grow(self, int: n) <- {
+        self.size <- self.size + n;
+        return self.size;
+    }
+    int: speed(self) <- {
+        return self.size * 10;
+    }
+}
+class Car <- {
+    Wheel: left <- Wheel();
+    Wheel: make(self) <- {
+        Wheel: w <- Wheel();
+        w.size <- 7;
+        return w;
+    }
+    Wheel: spare(self) <- {
+        return self.left;
+    }
+}
+int: main <- {
+    Car: r <- Car();
+    print(r.left.grow(2 * 2));
+    print(r.left.size);
+    print(r.left.power);
+    Wheel: a <- Wheel();
+    Wheel: b <- Wheel();
+    list<Wheel>: xs <- [a, b];
+    print(xs[0].speed());
+    print(xs[1].grow(1) + xs[0].grow(2));
+    print(r.make().grow(1));
+    print(r.make().speed());
+    if r.left.grow(1) > 5 <- {
+        print("big");
+    }
+    return r.left.speed() - 70;
+}
@@ -0,0 +1,30 @@
+// 内側のブロックで外側の名前を隠す変数
+int: total <- 10;
+
+int: add(int: n) <- {
+    int: sum <- total + n;
+    if n > 0 <- {
+        int: total <- 100;
+        sum <- sum + total;
+    }
+    total <- sum;
+    return total;
+}
+
+int: main <- {
+    int: k <- 1;
+    if k > 0 <- {
+        string: k <- "inner";
+        print(k);
+    }
+    print(k);
+    for i in 0..2 <- {
+        for i in 5..6 <- {
+            print(i);
+        }
+        print(i);
+    }
+    print(add(1));
+    print(total);
+    return 0;
+}
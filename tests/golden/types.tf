@@ -0,0 +1,34 @@
+const bool: VERBOSE <- false;
+bool: ready;
+string: label;
+int: count;
+
+bool: flip(bool: b <- true) <- {
+    return not b;
+}
+
+string: greet(string: name, int: times <- 1) <- {
+    return "hello {name} x{times}";
+}
+
+void: report() <- {
+    if ready <- {
+        print("ready");
+    } else <- {
+        print("not ready");
+    }
+    print("[{label}] {count}");
+}
+
+int: main <- {
+    report();
+    print(7 / 2);
+    print((7 + 1) / 2 * 3);
+    print(-7 / 2);
+    print(7 / 2.0);
+    print(flip() == false);
+    if not VERBOSE and flip(false) <- {
+        print(greet("tf", times = 2));
+    }
+    return 0;
+}
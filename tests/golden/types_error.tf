@@ -0,0 +1,6 @@
+Strng: name;
+
+int: main <- {
+    int: n <- true;
+    return 0;
+}
@@ -0,0 +1,13 @@
+class Base <- {
+    int: power <- 1;
+}
+
+class Fast : Base <- {
+    int: boost <- 2;
+}
+
+int: main <- {
+    Base: g <- Fast();
+    print(g.power);
+    return 0;
+}